@@ -8,16 +8,22 @@ use serenity::framework::standard::{CommandResult, macros::command};
 use serenity::model::prelude::Message;
 use serenity::prelude::{Context, TypeMapKey};
 
+use crate::invocation::Invocation;
+
 #[command]
 #[description("Gives you a nice message by linus torvalds :)")]
 async fn hate(ctx: &Context, msg: &Message) -> CommandResult {
+	handle_hate(ctx, &msg.into()).await
+}
+
+pub async fn handle_hate(ctx: &Context, invocation: &Invocation<'_>) -> CommandResult {
 	let data = ctx.data.read().await;
 	let messages = data.get::<HateMessage>().unwrap();
 
 	let mut rng = rand::rngs::StdRng::from_entropy();
 	let random_number = rng.gen_range(0..messages.len() as i64) as usize;
 
-	msg.channel_id.say(&ctx.http, &messages[random_number]).await?;
+	invocation.say(ctx, &messages[random_number]).await?;
 
 	Ok(())
 }
@@ -2,17 +2,23 @@ use serenity::framework::standard::{Args, CommandResult, macros::command};
 use serenity::model::channel::Message;
 use serenity::prelude::Context;
 
+use crate::invocation::Invocation;
+
 //TODO: Proper Error reply
 #[command]
 #[aliases("repl")]
 #[description("Evaluates a given mathematical expression")]
 #[example("2 - 3 * log(24/3) ^ 5")]
 pub async fn eval(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+	handle_eval(ctx, &msg.into(), args.rest()).await
+}
+
+pub async fn handle_eval(ctx: &Context, invocation: &Invocation<'_>, expression: &str) -> CommandResult {
 	let mut namespace = fasteval::EmptyNamespace; //no variables therefore empty namespace
 
-	let res = fasteval::ez_eval(args.rest(), &mut namespace)?;
+	let res = fasteval::ez_eval(expression, &mut namespace)?;
 
-	msg.channel_id.say(&ctx.http, &res.to_string()).await?;
+	invocation.say(ctx, &res.to_string()).await?;
 
 	Ok(())
 }
\ No newline at end of file
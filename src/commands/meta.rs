@@ -7,11 +7,16 @@ use serenity::prelude::*;
 
 use crate::ShardManagerContainer;
 use crate::config::ConfigData;
+use crate::invocation::Invocation;
 
 #[command]
 #[description("Replies with \"Pong!\"")]
 pub async fn ping(ctx: &Context, msg: &Message) -> CommandResult {
-	msg.reply(&ctx.http, "Pong!").await?;
+	handle_ping(ctx, &msg.into()).await
+}
+
+pub async fn handle_ping(ctx: &Context, invocation: &Invocation<'_>) -> CommandResult {
+	invocation.reply(ctx, "Pong!").await?;
 
 	Ok(())
 }
@@ -19,16 +24,18 @@ pub async fn ping(ctx: &Context, msg: &Message) -> CommandResult {
 #[command]
 #[description("Gives a link to invite the bot")]
 pub async fn invite(ctx: &Context, msg: &Message) -> CommandResult {
+	handle_invite(ctx, &msg.into()).await
+}
+
+pub async fn handle_invite(ctx: &Context, invocation: &Invocation<'_>) -> CommandResult {
 	let data = ctx.data.read().await;
 	let config_data = data.get::<ConfigData>().unwrap();
 	let invite_url = &config_data.general.invite_url;
 
-	msg.channel_id.send_message(&ctx.http, |message| {
-		message.embed(|embed|{
-			embed.title("Let me join your server :)");
-			embed.url(invite_url);
-			embed
-		})
+	invocation.send_embed(ctx, |embed|{
+		embed.title("Let me join your server :)");
+		embed.url(invite_url);
+		embed
 	}).await?;
 
 
@@ -38,6 +45,10 @@ pub async fn invite(ctx: &Context, msg: &Message) -> CommandResult {
 #[command]
 #[description("Replies with the current latency the bot has to discord.\nMay not be 100% accurate or up to date")]
 async fn latency(ctx: &Context, msg: &Message) -> CommandResult {
+	handle_latency(ctx, &msg.into()).await
+}
+
+pub async fn handle_latency(ctx: &Context, invocation: &Invocation<'_>) -> CommandResult {
 	// The shard manager is an interface for mutating, stopping, restarting, and
 	// retrieving information about shards.
 	let data = ctx.data.read().await;
@@ -45,7 +56,7 @@ async fn latency(ctx: &Context, msg: &Message) -> CommandResult {
 	let shard_manager = match data.get::<ShardManagerContainer>() {
 		Some(v) => v,
 		None => {
-			msg.reply(ctx, "There was a problem getting the shard manager").await?;
+			invocation.reply(ctx, "There was a problem getting the shard manager").await?;
 
 			return Ok(());
 		}
@@ -60,13 +71,13 @@ async fn latency(ctx: &Context, msg: &Message) -> CommandResult {
 	let runner = match runners.get(&ShardId(ctx.shard_id)) {
 		Some(runner) => runner,
 		None => {
-			msg.reply(ctx, "No shard found").await?;
+			invocation.reply(ctx, "No shard found").await?;
 
 			return Ok(());
 		}
 	};
 
-	msg.reply(ctx, &format!("The shard latency is {:?}", runner.latency.unwrap_or(Duration::from_secs(u64::MAX)))).await?;
+	invocation.reply(ctx, &format!("The shard latency is {:?}", runner.latency.unwrap_or(Duration::from_secs(u64::MAX)))).await?;
 
 	Ok(())
 }
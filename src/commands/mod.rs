@@ -1,5 +1,6 @@
 pub mod meta;
 pub mod math;
 pub mod hate;
+pub mod settings;
 #[cfg(feature = "music")]
 pub mod music;
\ No newline at end of file
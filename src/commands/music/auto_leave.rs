@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use serenity::model::id::GuildId;
+use serenity::prelude::{Context, Mutex, TypeMapKey};
+
+use crate::commands::music::backend::Backend;
+use crate::commands::music::leave::disconnect;
+use crate::commands::music::util::listeners;
+use crate::config::ConfigData;
+
+/// How often guilds are checked for not playing anything.
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Since when guilds are without listeners or without playback.
+/// Guilds that aren't in either map are fine.
+#[derive(Default)]
+pub struct AutoLeave {
+	alone_since: Mutex<HashMap<GuildId, Instant>>,
+	idle_since: Mutex<HashMap<GuildId, Instant>>,
+	watching: AtomicBool,
+}
+
+impl TypeMapKey for AutoLeave {
+	type Value = Arc<AutoLeave>;
+}
+
+/// Called for every voice state update, leaves after `alone_timeout` once the last listener left.
+pub async fn voice_state_update(ctx: &Context, guild_id: GuildId) {
+	let (auto_leave, alone_timeout) = {
+		let data = ctx.data.read().await;
+		let alone_timeout = data.get::<ConfigData>().unwrap().music.as_ref().map_or(0, |music| music.alone_timeout);
+
+		(data.get::<AutoLeave>().unwrap().clone(), alone_timeout)
+	};
+
+	if alone_timeout == 0 {
+		return;
+	}
+
+	let manager = songbird::get(ctx).await.unwrap().clone();
+	if manager.get(guild_id).is_none() {
+		return;
+	}
+
+	if !listeners(ctx, guild_id).await.is_empty() {
+		auto_leave.alone_since.lock().await.remove(&guild_id);
+
+		return;
+	}
+
+	let since = Instant::now();
+
+	{
+		let mut alone_since = auto_leave.alone_since.lock().await;
+
+		// Already waiting since an earlier update
+		if alone_since.contains_key(&guild_id) {
+			return;
+		}
+
+		alone_since.insert(guild_id, since);
+	}
+
+	let ctx = ctx.clone();
+
+	tokio::spawn(async move {
+		tokio::time::sleep(Duration::from_secs(alone_timeout)).await;
+
+		// Someone joined in the meantime, maybe leaving again later
+		if auto_leave.alone_since.lock().await.get(&guild_id) != Some(&since) {
+			return;
+		}
+
+		leave(&ctx, &auto_leave, guild_id, "Left the voice channel because nobody is listening anymore").await;
+	});
+}
+
+/// Leaves guilds that didn't play anything for `idle_timeout` minutes.
+/// Runs until the bot stops, only the first call does anything.
+pub async fn watch_idle(ctx: Context) {
+	let (auto_leave, idle_timeout) = {
+		let data = ctx.data.read().await;
+		let idle_timeout = data.get::<ConfigData>().unwrap().music.as_ref().map_or(0, |music| music.idle_timeout);
+
+		(data.get::<AutoLeave>().unwrap().clone(), idle_timeout)
+	};
+
+	// `ready` is sent again after reconnecting
+	if idle_timeout == 0 || auto_leave.watching.swap(true, Ordering::SeqCst) {
+		return;
+	}
+
+	let idle_timeout = Duration::from_secs(idle_timeout * 60);
+	let manager = songbird::get(&ctx).await.unwrap().clone();
+	let backend = ctx.data.read().await.get::<Backend>().unwrap().clone();
+
+	loop {
+		tokio::time::sleep(IDLE_CHECK_INTERVAL).await;
+
+		for guild_id in ctx.cache.guilds().await {
+			if manager.get(guild_id).is_none() {
+				auto_leave.idle_since.lock().await.remove(&guild_id);
+
+				continue;
+			}
+
+			let playing = backend.now_playing(guild_id).await.is_some_and(|now_playing| !now_playing.paused);
+
+			if playing {
+				auto_leave.idle_since.lock().await.remove(&guild_id);
+
+				continue;
+			}
+
+			let idle_since = *auto_leave.idle_since.lock().await.entry(guild_id).or_insert_with(Instant::now);
+
+			if idle_since.elapsed() >= idle_timeout {
+				leave(&ctx, &auto_leave, guild_id, "Left the voice channel because nothing was played for a while").await;
+			}
+		}
+	}
+}
+
+async fn leave(ctx: &Context, auto_leave: &AutoLeave, guild_id: GuildId, reason: &str) {
+	auto_leave.alone_since.lock().await.remove(&guild_id);
+	auto_leave.idle_since.lock().await.remove(&guild_id);
+
+	// Left through the `leave` command in the meantime
+	if songbird::get(ctx).await.unwrap().get(guild_id).is_none() {
+		return;
+	}
+
+	let backend = ctx.data.read().await.get::<Backend>().unwrap().clone();
+
+	// Has to happen before disconnecting, that removes the channel to announce in
+	backend.announce(guild_id, reason).await;
+
+	log::info!("Leaving the voice channel of guild {}: {}", guild_id, reason);
+
+	if let Err(why) = disconnect(ctx, guild_id).await {
+		log::warn!("Could not leave the voice channel of guild {}: {:?}", guild_id, why);
+	}
+}
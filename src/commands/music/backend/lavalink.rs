@@ -0,0 +1,629 @@
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use lavalink_rs::error::LavalinkError;
+use lavalink_rs::LavalinkClient;
+use lavalink_rs::model::{Info, LavalinkResult, Track, TrackQueue, Tracks};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use serenity::async_trait;
+use serenity::framework::standard::CommandResult;
+use serenity::model::channel::Channel;
+use serenity::model::id::{ChannelId, GuildId, UserId};
+use serenity::prelude::{Context, RwLock, TypeMap};
+use songbird::error::JoinError;
+
+use crate::commands::music::backend::{Backend, ClipPlayback, ConnectError, MediaRoots, MusicBackend, NowPlaying, QueuedTrack};
+use crate::commands::music::filter::{apply_filters, Filters};
+use crate::commands::music::nodes::{LavalinkNodes, resume_queue};
+use crate::commands::music::panel;
+use crate::commands::music::remove::Removal;
+use crate::commands::music::skip::SkipVotes;
+use crate::commands::music::soundboard::PlayingClip;
+use crate::commands::music::util::{is_link, loop_mode, LoopMode, PlayingTrack, requester, set_caller_channel};
+use crate::commands::music::volume::apply_volume;
+use crate::database::Database;
+
+/// Plays music on the lavalink nodes.
+pub struct LavalinkBackend {
+	nodes: Arc<LavalinkNodes>,
+	roots: MediaRoots,
+}
+
+impl LavalinkBackend {
+	pub fn new(nodes: Arc<LavalinkNodes>, roots: MediaRoots) -> Self {
+		LavalinkBackend { nodes, roots }
+	}
+
+	async fn client(&self, guild_id: GuildId) -> Option<LavalinkClient> {
+		self.nodes.client(guild_id).await
+	}
+
+}
+
+#[async_trait]
+impl MusicBackend for LavalinkBackend {
+	async fn is_available(&self, guild_id: GuildId) -> bool {
+		self.client(guild_id).await.is_some()
+	}
+
+	async fn players(&self) -> Vec<GuildId> {
+		self.nodes.sessions().await.into_iter().map(|(guild_id, _)| guild_id).collect()
+	}
+
+	async fn connect(
+		&self,
+		ctx: &Context,
+		guild_id: GuildId,
+		voice_channel_id: ChannelId,
+		text_channel_id: ChannelId,
+	) -> Result<(), ConnectError> {
+		connect(ctx, &self.nodes, guild_id, voice_channel_id, text_channel_id).await.map(|_| ())
+	}
+
+	async fn disconnect(&self, ctx: &Context, guild_id: GuildId) -> Result<(), JoinError> {
+		let manager = songbird::get(ctx).await.unwrap().clone();
+		let result = manager.remove(guild_id).await;
+
+		//Clean up the player, event loops and data on leave
+		//See https://docs.rs/lavalink-rs/0.7.2/lavalink_rs/struct.LavalinkClient.html#method.destroy
+		if let Some(lava_client) = self.client(guild_id).await {
+			if let Some(data) = self.data(guild_id).await {
+				panel::close(&data).await;
+			}
+
+			// Fails if the node went down, the local state still has to be cleaned up
+			if let Err(why) = lava_client.destroy(guild_id).await {
+				log::warn!("Could not destroy the player of guild {}: {:?}", guild_id, why);
+			}
+
+			{
+				let nodes = lava_client.nodes().await;
+				nodes.remove(&guild_id.0);
+
+				let loops = lava_client.loops().await;
+				loops.remove(&guild_id.0);
+			}
+		}
+
+		self.nodes.release(guild_id).await;
+
+		result
+	}
+
+	async fn data(&self, guild_id: GuildId) -> Option<Arc<RwLock<TypeMap>>> {
+		let lava_client = self.client(guild_id).await?;
+		let data = lava_client.nodes().await.get(&guild_id.0).map(|node| Arc::clone(&node.data));
+
+		data
+	}
+
+	async fn enqueue(&self, guild_id: GuildId, query: &str, requester: UserId, next: bool) -> CommandResult<usize> {
+		let lava_client = match self.client(guild_id).await {
+			Some(lava_client) => lava_client,
+			None => return Ok(0),
+		};
+
+		let query_information = find_tracks(&lava_client, query).await?;
+
+		let found = if is_link(query) {
+			&query_information.tracks[..]
+		} else {
+			&query_information.tracks[..query_information.tracks.len().min(1)]
+		};
+
+		queue_found(&lava_client, guild_id, found, requester, next).await;
+
+		Ok(found.len())
+	}
+
+	async fn enqueue_file(&self, guild_id: GuildId, path: &Path, requester: UserId) -> CommandResult<usize> {
+		let (lava_client, path) = match (self.client(guild_id).await, self.roots.resolve(path).await) {
+			(Some(lava_client), Some(path)) => (lava_client, path),
+			_ => return Ok(0),
+		};
+
+		// Needs the local source of lavalink, which reads the file from the same path on its own host
+		let found = lava_client.get_tracks(path.to_string_lossy()).await?.tracks;
+		let found = &found[..found.len().min(1)];
+
+		queue_found(&lava_client, guild_id, found, requester, false).await;
+
+		Ok(found.len())
+	}
+
+	async fn enqueue_tracks(&self, guild_id: GuildId, tracks: &[QueuedTrack]) -> CommandResult<usize> {
+		let lava_client = match self.client(guild_id).await {
+			Some(lava_client) => lava_client,
+			None => return Ok(0),
+		};
+
+		for track in tracks {
+			let track = track_queue(track);
+
+			let mut play = lava_client.play(guild_id, track.track);
+			if let Some(requester) = track.requester {
+				play = play.requester(requester);
+			}
+
+			play.queue().await?;
+		}
+
+		Ok(tracks.len())
+	}
+
+	async fn resume(&self, guild_id: GuildId, tracks: &[QueuedTrack], position: Option<u64>, paused: bool) -> CommandResult {
+		if let Some(lava_client) = self.client(guild_id).await {
+			let filters = lava_client.nodes().await.get(&guild_id.0).map(|node| Arc::clone(&node.data));
+			let filters = match filters {
+				Some(data) => data.read().await.get::<Filters>().cloned(),
+				None => None,
+			};
+
+			if let Some(filters) = filters.filter(|filters| !filters.is_empty()) {
+				apply_filters(&lava_client, guild_id, &filters).await?;
+			}
+
+			resume_queue(&lava_client, guild_id, tracks.iter().map(track_queue).collect(), position, paused).await?;
+		}
+
+		Ok(())
+	}
+
+	async fn play_clip(&self, guild_id: GuildId, path: &Path, requester: UserId) -> CommandResult<ClipPlayback> {
+		let (lava_client, data) = match (self.client(guild_id).await, self.data(guild_id).await) {
+			(Some(lava_client), Some(data)) => (lava_client, data),
+			_ => return Ok(ClipPlayback::Failed),
+		};
+
+		if data.read().await.contains_key::<PlayingClip>() {
+			return Ok(ClipPlayback::AlreadyPlaying);
+		}
+
+		let path = match self.roots.resolve(path).await {
+			Some(path) => path,
+			None => return Ok(ClipPlayback::Failed),
+		};
+
+		let clip = match lava_client.get_tracks(path.to_string_lossy()).await?.tracks.into_iter().next() {
+			Some(clip) => clip,
+			None => return Ok(ClipPlayback::Failed),
+		};
+
+		let (current, paused) = match lava_client.nodes().await.get(&guild_id.0) {
+			Some(node) => (node.now_playing.clone(), node.is_paused),
+			None => return Ok(ClipPlayback::Failed),
+		};
+
+		{
+			let mut data = data.write().await;
+			data.insert::<PlayingClip>(PlayingClip { track: clip.track.clone(), paused });
+			// The interrupted song isn't over, its end must not put it into the history or loop it
+			data.remove::<PlayingTrack>();
+		}
+
+		match current {
+			Some(mut current) => {
+				// Lavalink only reports the position every few seconds, the song continues close to where it was
+				current.start_time = current.track.info.as_ref().map_or(current.start_time, |info| info.position);
+
+				if let Some(mut node) = lava_client.nodes().await.get_mut(&guild_id.0) {
+					let index = node.queue.len().min(1);
+					node.queue.insert(index, TrackQueue {
+						track: clip,
+						start_time: 0,
+						end_time: None,
+						requester: Some(requester.into()),
+					});
+					node.queue.insert(index + 1, current);
+				}
+
+				// The queue loop of lavalink-rs starts the clip once nothing is playing anymore
+				lava_client.skip(guild_id).await;
+			}
+			None => add_link_to_queue(&lava_client, guild_id, clip, requester).await?,
+		}
+
+		// The player is paused again once the clip is over
+		if paused {
+			self.set_paused(guild_id, false).await?;
+		}
+
+		Ok(ClipPlayback::Started)
+	}
+
+	async fn replay(&self, guild_id: GuildId, track: &QueuedTrack) -> CommandResult<bool> {
+		let lava_client = match self.client(guild_id).await {
+			Some(lava_client) => lava_client,
+			None => return Ok(false),
+		};
+
+		let current = lava_client.nodes().await.get(&guild_id.0).and_then(|node| node.now_playing.clone());
+
+		match current {
+			Some(mut current) => {
+				current.start_time = 0;
+
+				if let Some(mut node) = lava_client.nodes().await.get_mut(&guild_id.0) {
+					let index = node.queue.len().min(1);
+					node.queue.insert(index, track_queue(track));
+					node.queue.insert(index + 1, current);
+				}
+
+				// Ends the current song, which starts the one that is played again
+				lava_client.skip(guild_id).await;
+			}
+			None => {
+				self.enqueue_tracks(guild_id, std::slice::from_ref(track)).await?;
+			}
+		}
+
+		Ok(true)
+	}
+
+	async fn now_playing(&self, guild_id: GuildId) -> Option<NowPlaying> {
+		let lava_client = self.client(guild_id).await?;
+		let nodes = lava_client.nodes().await;
+		let node = nodes.get(&guild_id.0)?;
+		let track = node.now_playing.as_ref()?;
+
+		Some(NowPlaying {
+			track: queued_track(track),
+			position: track.track.info.as_ref().map_or(0, |info| info.position),
+			paused: node.is_paused,
+		})
+	}
+
+	async fn queue(&self, guild_id: GuildId) -> Vec<QueuedTrack> {
+		let lava_client = match self.client(guild_id).await {
+			Some(lava_client) => lava_client,
+			None => return Vec::new(),
+		};
+
+		// While a track plays it is the first one in the queue
+		let queue = match lava_client.nodes().await.get(&guild_id.0) {
+			Some(node) => node.queue.iter().skip(1).map(queued_track).collect(),
+			None => Vec::new(),
+		};
+
+		queue
+	}
+
+	async fn skip(&self, guild_id: GuildId) -> CommandResult<Option<QueuedTrack>> {
+		let lava_client = match self.client(guild_id).await {
+			Some(lava_client) => lava_client,
+			None => return Ok(None),
+		};
+
+		let track = match lava_client.skip(guild_id).await {
+			Some(track) => track,
+			None => return Ok(None),
+		};
+
+		let (clip, loop_mode) = match self.data(guild_id).await {
+			Some(data) => {
+				let loop_mode = loop_mode(&data).await;
+				let mut data = data.write().await;
+				data.remove::<SkipVotes>();
+
+				(data.get::<PlayingClip>().is_some_and(|clip| clip.track == track.track.track), loop_mode)
+			}
+			None => (false, LoopMode::Off),
+		};
+
+		// Skipped songs stay part of the loop when the whole queue is looped, soundboard clips never were part of it
+		if !clip && loop_mode == LoopMode::Queue {
+			if let Some(mut node) = lava_client.nodes().await.get_mut(guild_id.as_u64()) {
+				let mut track = track.clone();
+				track.start_time = 0;
+				node.queue.push(track);
+			}
+		}
+
+		let empty = lava_client.nodes().await.get(guild_id.as_u64()).is_some_and(|node| node.queue.is_empty());
+		if empty {
+			lava_client.stop(guild_id).await?;
+		}
+
+		Ok(Some(queued_track(&track)))
+	}
+
+	async fn skip_to(&self, guild_id: GuildId, index: usize) -> CommandResult<Option<QueuedTrack>> {
+		let (lava_client, data) = match (self.client(guild_id).await, self.data(guild_id).await) {
+			(Some(lava_client), Some(data)) => (lava_client, data),
+			_ => return Ok(None),
+		};
+
+		let loop_mode = loop_mode(&data).await;
+
+		let track = match lava_client.nodes().await.get_mut(&guild_id.0) {
+			// The current song is at index 0
+			Some(mut node) if index + 1 < node.queue.len() => {
+				let queue = &mut node.queue;
+				let skipped = queue.drain(1..index + 1).collect::<Vec<_>>();
+
+				// Skipped songs stay part of the loop when the whole queue is looped
+				if loop_mode == LoopMode::Queue {
+					let mut current = queue[0].clone();
+					current.start_time = 0;
+					queue.push(current);
+					queue.extend(skipped);
+				}
+
+				queued_track(&queue[1])
+			}
+			_ => return Ok(None),
+		};
+
+		// Ends the current song, which starts the one that is now next
+		lava_client.skip(guild_id).await;
+
+		Ok(Some(track))
+	}
+
+	async fn move_track(&self, guild_id: GuildId, from: usize, to: usize) -> CommandResult<Option<QueuedTrack>> {
+		let lava_client = match self.client(guild_id).await {
+			Some(lava_client) => lava_client,
+			None => return Ok(None),
+		};
+
+		let track = match lava_client.nodes().await.get_mut(&guild_id.0) {
+			// The current song is at index 0 and can't be moved
+			Some(mut node) if from + 1 < node.queue.len() && to + 1 < node.queue.len() => {
+				let track = node.queue.remove(from + 1);
+				let moved = queued_track(&track);
+				node.queue.insert(to + 1, track);
+
+				Some(moved)
+			}
+			_ => None,
+		};
+
+		Ok(track)
+	}
+
+	async fn set_paused(&self, guild_id: GuildId, paused: bool) -> CommandResult {
+		let lava_client = match self.client(guild_id).await {
+			Some(lava_client) => lava_client,
+			None => return Ok(()),
+		};
+
+		if paused {
+			lava_client.pause(guild_id).await?;
+		} else {
+			lava_client.resume(guild_id).await?;
+		}
+
+		// Kept so the player can be paused again when it is moved to another node
+		if let Some(mut node) = lava_client.nodes().await.get_mut(&guild_id.0) {
+			node.is_paused = paused;
+		}
+
+		Ok(())
+	}
+
+	async fn clear(&self, guild_id: GuildId) -> CommandResult {
+		if let Some(lava_client) = self.client(guild_id).await {
+			if let Some(mut node) = lava_client.nodes().await.get_mut(&guild_id.0) {
+				node.queue.truncate(1);
+			}
+		}
+
+		Ok(())
+	}
+
+	async fn remove(&self, guild_id: GuildId, removal: &Removal) -> CommandResult<Option<Vec<(usize, QueuedTrack)>>> {
+		let lava_client = match self.client(guild_id).await {
+			Some(lava_client) => lava_client,
+			None => return Ok(Some(Vec::new())),
+		};
+
+		let nodes = lava_client.nodes().await;
+
+		let mut node = match nodes.get_mut(&guild_id.0) {
+			Some(node) => node,
+			None => return Ok(Some(Vec::new())),
+		};
+
+		// The current song comes first
+		let songs = node.queue.iter().skip(1).map(queued_track).collect::<Vec<_>>();
+
+		let indexes = match removal.indexes(&songs) {
+			Some(indexes) => indexes,
+			None => return Ok(None),
+		};
+
+		// Removing from the back keeps the remaining indexes valid
+		for index in indexes.iter().rev() {
+			node.queue.remove(index + 1);
+		}
+
+		Ok(Some(indexes.into_iter().map(|index| (index, songs[index].clone())).collect()))
+	}
+
+	async fn shuffle(&self, guild_id: GuildId) -> CommandResult {
+		if let Some(lava_client) = self.client(guild_id).await {
+			if let Some(mut node) = lava_client.nodes().await.get_mut(&guild_id.0) {
+				// The current song stays where it is
+				if node.queue.len() > 1 {
+					node.queue[1..].shuffle(&mut StdRng::from_entropy());
+				}
+			}
+		}
+
+		Ok(())
+	}
+
+	async fn search(&self, guild_id: GuildId, query: &str, limit: usize) -> CommandResult<Vec<QueuedTrack>> {
+		let lava_client = match self.client(guild_id).await {
+			Some(lava_client) => lava_client,
+			None => return Ok(Vec::new()),
+		};
+
+		let found = find_tracks(&lava_client, query).await?;
+		let limit = if is_link(query) { found.tracks.len() } else { limit };
+
+		Ok(found.tracks
+			.into_iter()
+			.filter(|track| track.info.is_some())
+			.take(limit)
+			.map(|track| queued_track(&TrackQueue { track, start_time: 0, end_time: None, requester: None }))
+			.collect())
+	}
+
+	async fn volume(&self, guild_id: GuildId) -> Option<u16> {
+		let lava_client = self.client(guild_id).await?;
+		let volume = lava_client.nodes().await.get(&guild_id.0).map(|node| node.volume);
+
+		volume
+	}
+
+	async fn set_volume(&self, guild_id: GuildId, volume: u16) -> CommandResult {
+		if let Some(lava_client) = self.client(guild_id).await {
+			apply_volume(&lava_client, guild_id, volume).await?;
+		}
+
+		Ok(())
+	}
+
+	async fn seek(&self, guild_id: GuildId, position: u64) -> CommandResult {
+		let lava_client = match self.client(guild_id).await {
+			Some(lava_client) => lava_client,
+			None => return Ok(()),
+		};
+
+		lava_client.seek(guild_id, Duration::from_millis(position)).await?;
+
+		// Lavalink only sends position updates every few seconds, jumping again right away would start from an old one
+		if let Some(mut node) = lava_client.nodes().await.get_mut(&guild_id.0) {
+			if let Some(info) = node.now_playing.as_mut().and_then(|track| track.track.info.as_mut()) {
+				info.position = position;
+			}
+		}
+
+		Ok(())
+	}
+}
+
+/// Joins the voice channel and creates a player for the guild on one of the lavalink nodes.
+/// Announcements go to `text_channel_id`.
+async fn connect(
+	ctx: &Context,
+	lavalink_nodes: &LavalinkNodes,
+	guild_id: GuildId,
+	voice_channel_id: ChannelId,
+	text_channel_id: ChannelId,
+) -> Result<LavalinkClient, ConnectError> {
+	// Regions are set per voice channel, `None` means discord picks one automatically
+	let region = match voice_channel_id.to_channel_cached(&ctx.cache).await {
+		Some(Channel::Guild(channel)) => channel.rtc_region,
+		_ => None,
+	};
+
+	let lava_client = lavalink_nodes.assign(guild_id, region.as_deref()).await.ok_or(ConnectError::NoNode)?;
+
+	let manager = songbird::get(ctx).await.unwrap().clone();
+
+	let connection_info = match manager.join_gateway(guild_id, voice_channel_id).await.1 {
+		Ok(connection_info) => connection_info,
+		Err(why) => {
+			lavalink_nodes.release(guild_id).await;
+
+			return Err(ConnectError::Join(why));
+		}
+	};
+
+	lava_client.create_session(&connection_info).await.map_err(|why| ConnectError::Other(why.into()))?;
+
+	if let Some(node) = lava_client.nodes().await.get(guild_id.as_u64()) {
+		set_caller_channel(&node, ctx.http.clone(), text_channel_id).await;
+	}
+
+	// The event handler of lavalink-rs doesn't get the context, it finds the backend in the player data
+	let backend = ctx.data.read().await.get::<Backend>().cloned();
+	let data = lava_client.nodes().await.get(guild_id.as_u64()).map(|node| Arc::clone(&node.data));
+	if let (Some(backend), Some(data)) = (backend, data) {
+		data.write().await.insert::<Backend>(backend);
+	}
+
+	let database = ctx.data.read().await.get::<Database>().unwrap().clone();
+	if let Some(volume) = database.guild_volume(guild_id).await.map_err(|why| ConnectError::Other(why.into()))? {
+		apply_volume(&lava_client, guild_id, volume).await.map_err(|why| ConnectError::Other(why.into()))?;
+	}
+
+	Ok(lava_client)
+}
+
+/// Queues the tracks, or puts them right after the current song if `next` is set.
+async fn queue_found(lava_client: &LavalinkClient, guild_id: GuildId, found: &[Track], requester: UserId, next: bool) {
+	let queued_before = lava_client.nodes().await.get(&guild_id.0).map_or(0, |node| node.queue.len());
+
+	for track in found {
+		log::trace!("Queueing track {:?}", track);
+		if let Err(why) = add_link_to_queue(lava_client, guild_id, track.clone(), requester).await {
+			log::error!("{}", why)
+		}
+	}
+
+	// Nothing to skip ahead of when the first new track started playing right away
+	if next && queued_before > 0 {
+		if let Some(mut node) = lava_client.nodes().await.get_mut(&guild_id.0) {
+			let added = node.queue.len().saturating_sub(queued_before);
+			node.queue[1..].rotate_right(added);
+		}
+	}
+}
+
+/// Loads the tracks of an http(s) link, or searches youtube for anything else.
+/// Other queries must not reach lavalink as they are, its local source would open any file on its host.
+async fn find_tracks(lava_client: &LavalinkClient, query: &str) -> LavalinkResult<Tracks> {
+	if is_link(query) {
+		lava_client.get_tracks(query).await
+	} else {
+		lava_client.search_tracks(query).await
+	}
+}
+
+async fn add_link_to_queue(lava_client: &LavalinkClient, guild_id: GuildId, track: Track, requester: UserId) -> Result<(), LavalinkError> {
+	lava_client.play(guild_id, track)
+		.requester(requester)
+		.queue()
+		.await?;
+	Ok(())
+}
+
+pub fn queued_track(track: &TrackQueue) -> QueuedTrack {
+	let info = track.track.info.as_ref();
+
+	QueuedTrack {
+		id: track.track.track.clone(),
+		title: info.map_or_else(|| "Unknown".to_string(), |info| info.title.clone()),
+		uri: info.map(|info| info.uri.clone()),
+		length: info.map_or(0, |info| info.length),
+		is_stream: info.is_some_and(|info| info.is_stream),
+		is_seekable: info.is_some_and(|info| info.is_seekable),
+		requester: requester(track),
+	}
+}
+
+/// Lavalink plays the song from the encoded track in the `id`, the info only tells the commands about it.
+fn track_queue(track: &QueuedTrack) -> TrackQueue {
+	TrackQueue {
+		track: Track {
+			track: track.id.clone(),
+			info: Some(Info {
+				is_seekable: track.is_seekable,
+				is_stream: track.is_stream,
+				length: track.length,
+				title: track.title.clone(),
+				uri: track.uri.clone().unwrap_or_default(),
+				..Info::default()
+			}),
+		},
+		start_time: 0,
+		end_time: None,
+		requester: track.requester.map(Into::into),
+	}
+}
@@ -0,0 +1,231 @@
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use serenity::async_trait;
+use serenity::framework::standard::{CommandError, CommandResult};
+use serenity::model::id::{ChannelId, GuildId, UserId};
+use serenity::prelude::{Context, RwLock, TypeMap, TypeMapKey};
+use songbird::error::JoinError;
+
+use crate::commands::music::remove::Removal;
+use crate::commands::music::util::{CallerChannel, LoopMode};
+use crate::config::Music;
+
+pub mod lavalink;
+pub mod native;
+
+/// The backend the music commands play through, see [`MusicBackend`].
+pub struct Backend;
+
+impl TypeMapKey for Backend {
+	type Value = Arc<dyn MusicBackend>;
+}
+
+/// The directories audio files of the bot host are played from, the backends open no file outside of them.
+#[derive(Clone)]
+pub struct MediaRoots {
+	directories: Vec<PathBuf>,
+}
+
+impl MediaRoots {
+	/// The media directory and the soundboard directory of the config.
+	pub fn new(music: &Music) -> Self {
+		let directories = music.media_directory
+			.iter()
+			.chain(std::iter::once(&music.soundboard_directory))
+			.map(PathBuf::from)
+			.collect();
+
+		MediaRoots { directories }
+	}
+
+	/// The canonical path of the file, `None` if it doesn't exist or is outside of the directories.
+	pub async fn resolve(&self, path: &Path) -> Option<PathBuf> {
+		let path = tokio::fs::canonicalize(path).await.ok()?;
+
+		for directory in &self.directories {
+			// The soundboard directory is only created with the first clip
+			if let Ok(directory) = tokio::fs::canonicalize(directory).await {
+				if path.starts_with(&directory) {
+					return Some(path);
+				}
+			}
+		}
+
+		log::warn!("Refusing to play {}, it is outside of the media and soundboard directories", path.display());
+
+		None
+	}
+}
+
+/// A song in the queue, the same no matter what plays it.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct QueuedTrack {
+	/// Tells tracks apart, even ones with the same title. Lavalink plays the song again from it.
+	pub id: String,
+	pub title: String,
+	pub uri: Option<String>,
+	/// In milliseconds.
+	pub length: u64,
+	pub is_stream: bool,
+	pub is_seekable: bool,
+	pub requester: Option<UserId>,
+}
+
+/// The song that is playing right now.
+pub struct NowPlaying {
+	pub track: QueuedTrack,
+	/// In milliseconds.
+	pub position: u64,
+	pub paused: bool,
+}
+
+/// What became of a soundboard clip that was asked to play.
+pub enum ClipPlayback {
+	Started,
+	/// Only one clip plays at a time.
+	AlreadyPlaying,
+	/// The file could not be played, or the bot isn't connected.
+	Failed,
+}
+
+/// Why the bot could not connect to a voice channel.
+pub enum ConnectError {
+	/// None of the music servers are reachable.
+	NoNode,
+	Join(JoinError),
+	Other(CommandError),
+}
+
+impl Display for ConnectError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			ConnectError::NoNode => write!(f, "None of the music servers are reachable right now, try again later"),
+			ConnectError::Join(why) => write!(f, "Error joining the channel: {}", why),
+			ConnectError::Other(why) => write!(f, "Error joining the channel: {}", why),
+		}
+	}
+}
+
+/// Plays the music of the guilds.
+///
+/// Lavalink does the work on external servers, songbird inside the bot. Only the filters need
+/// lavalink, that command talks to it directly instead.
+#[async_trait]
+pub trait MusicBackend: Send + Sync {
+	/// Whether the guild can play music right now.
+	async fn is_available(&self, guild_id: GuildId) -> bool;
+
+	/// The guilds that have a player.
+	async fn players(&self) -> Vec<GuildId>;
+
+	/// Joins the voice channel, announcements go to `text_channel_id`.
+	async fn connect(
+		&self,
+		ctx: &Context,
+		guild_id: GuildId,
+		voice_channel_id: ChannelId,
+		text_channel_id: ChannelId,
+	) -> Result<(), ConnectError>;
+
+	/// Leaves the voice channel of the guild and cleans up its player.
+	/// The cleanup happens even if leaving fails, the error is returned afterwards.
+	async fn disconnect(&self, ctx: &Context, guild_id: GuildId) -> Result<(), JoinError>;
+
+	/// Things the commands remember about the guild's player, like the loop mode.
+	/// Gone once the bot leaves, `None` while it isn't connected.
+	async fn data(&self, guild_id: GuildId) -> Option<Arc<RwLock<TypeMap>>>;
+
+	/// Adds the songs of an http(s) link or the first search result to the end of the queue,
+	/// or right after the current song if `next` is set. Returns how many songs were added.
+	/// Anything that isn't a link is searched for, it is never opened as a file.
+	async fn enqueue(&self, guild_id: GuildId, query: &str, requester: UserId, next: bool) -> CommandResult<usize>;
+
+	/// Adds an audio file of the bot host to the end of the queue, if it is inside the [`MediaRoots`].
+	/// Returns how many songs were added.
+	async fn enqueue_file(&self, guild_id: GuildId, path: &Path, requester: UserId) -> CommandResult<usize>;
+
+	/// Adds songs that were in a queue before to the end of it, like the ones of a playlist.
+	/// Returns how many songs were added.
+	async fn enqueue_tracks(&self, guild_id: GuildId, tracks: &[QueuedTrack]) -> CommandResult<usize>;
+
+	/// Continues a queue that was saved before, its first song starts at `position`.
+	/// Filters stored in the player data before are applied again.
+	async fn resume(&self, guild_id: GuildId, tracks: &[QueuedTrack], position: Option<u64>, paused: bool) -> CommandResult;
+
+	/// Plays a soundboard clip right away. The current song is paused meanwhile and continues where it was afterwards.
+	/// The file has to be inside the [`MediaRoots`].
+	async fn play_clip(&self, guild_id: GuildId, path: &Path, requester: UserId) -> CommandResult<ClipPlayback>;
+
+	/// Plays a song that was played before right away, the current song starts over afterwards.
+	/// Returns whether the song could be played.
+	async fn replay(&self, guild_id: GuildId, track: &QueuedTrack) -> CommandResult<bool>;
+
+	async fn now_playing(&self, guild_id: GuildId) -> Option<NowPlaying>;
+
+	/// The songs after the current one.
+	async fn queue(&self, guild_id: GuildId) -> Vec<QueuedTrack>;
+
+	/// Ends the current song, returns it if there was one.
+	async fn skip(&self, guild_id: GuildId) -> CommandResult<Option<QueuedTrack>>;
+
+	/// Ends the current song and continues with the one at the index of [`MusicBackend::queue`],
+	/// the songs before it are removed. Returns the new song, `None` if the index is out of range.
+	async fn skip_to(&self, guild_id: GuildId, index: usize) -> CommandResult<Option<QueuedTrack>>;
+
+	/// Moves a song from one index of [`MusicBackend::queue`] to another.
+	/// Returns the song, `None` if an index is out of range.
+	async fn move_track(&self, guild_id: GuildId, from: usize, to: usize) -> CommandResult<Option<QueuedTrack>>;
+
+	async fn set_paused(&self, guild_id: GuildId, paused: bool) -> CommandResult;
+
+	/// Removes every song after the current one.
+	async fn clear(&self, guild_id: GuildId) -> CommandResult;
+
+	/// Removes songs after the current one, looking them up and removing them at once so the queue can't change in between.
+	/// Returns the removed songs with their indexes of [`MusicBackend::queue`], `None` if a position is not in the queue.
+	async fn remove(&self, guild_id: GuildId, removal: &Removal) -> CommandResult<Option<Vec<(usize, QueuedTrack)>>>;
+
+	/// Puts the songs after the current one into a random order.
+	async fn shuffle(&self, guild_id: GuildId) -> CommandResult;
+
+	/// The songs of a link, or at most `limit` songs found for the query.
+	/// They can be added with [`MusicBackend::enqueue_tracks`].
+	async fn search(&self, guild_id: GuildId, query: &str, limit: usize) -> CommandResult<Vec<QueuedTrack>>;
+
+	/// Changes what happens to songs after they finished playing.
+	async fn set_loop_mode(&self, guild_id: GuildId, mode: LoopMode) {
+		if let Some(data) = self.data(guild_id).await {
+			data.write().await.insert::<LoopMode>(mode);
+		}
+	}
+
+	/// In percent, `None` while the bot isn't connected.
+	async fn volume(&self, guild_id: GuildId) -> Option<u16>;
+
+	/// In percent, from 0 to `MAX_VOLUME`.
+	async fn set_volume(&self, guild_id: GuildId, volume: u16) -> CommandResult;
+
+	/// Jumps to the position in the current song, in milliseconds.
+	async fn seek(&self, guild_id: GuildId, position: u64) -> CommandResult;
+
+	/// Posts a message in the channel music was last requested from.
+	async fn announce(&self, guild_id: GuildId, content: &str) {
+		if let Some(data) = self.data(guild_id).await {
+			announce_in(&data, guild_id, content).await;
+		}
+	}
+}
+
+/// Posts a message in the `CallerChannel` of the player data.
+pub async fn announce_in(data: &RwLock<TypeMap>, guild_id: GuildId, content: &str) {
+	let data = data.read().await;
+
+	if let Some(caller_channel) = data.get::<CallerChannel>() {
+		if let Err(why) = caller_channel.channel_id.say(&caller_channel.http, content).await {
+			log::warn!("Could not post in the music channel of guild {}: {:?}", guild_id, why);
+		}
+	}
+}
@@ -0,0 +1,747 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use serenity::async_trait;
+use serenity::framework::standard::CommandResult;
+use serenity::model::id::{ChannelId, GuildId, UserId};
+use serenity::prelude::{Context, Mutex, RwLock, TypeMap, TypeMapKey};
+use songbird::{create_player, Event, EventContext, EventHandler as VoiceEventHandler, Songbird, TrackEvent};
+use songbird::error::JoinError;
+use songbird::input::{Input, Metadata, Restartable};
+use songbird::tracks::{PlayMode, TrackHandle, TrackQueue};
+
+use crate::commands::music::backend::{Backend, ClipPlayback, ConnectError, MediaRoots, MusicBackend, NowPlaying, QueuedTrack};
+use crate::commands::music::history::History;
+use crate::commands::music::panel;
+use crate::commands::music::remove::Removal;
+use crate::commands::music::skip::SkipVotes;
+use crate::commands::music::soundboard::PlayingClip;
+use crate::commands::music::util::{CallerChannel, is_link, loop_mode, LoopMode};
+use crate::commands::music::volume::DEFAULT_VOLUME;
+use crate::database::Database;
+
+/// Plays music with songbird's own queue, using youtube-dl and ffmpeg.
+/// Needs no lavalink server, but can't apply filters.
+pub struct SongbirdBackend {
+	songbird: Arc<Songbird>,
+	/// The player data of every guild the bot is connected in.
+	players: Mutex<HashMap<GuildId, Arc<RwLock<TypeMap>>>>,
+	roots: MediaRoots,
+}
+
+/// Who asked for a track, kept in the typemap of its handle.
+struct Requester;
+
+impl TypeMapKey for Requester {
+	type Value = UserId;
+}
+
+/// The volume of the guild in percent, new tracks start with it.
+struct Volume;
+
+impl TypeMapKey for Volume {
+	type Value = u16;
+}
+
+/// The call and player data of a guild, what the events of its tracks need to add songs to the queue.
+#[derive(Clone)]
+struct Player {
+	songbird: Arc<Songbird>,
+	guild_id: GuildId,
+	data: Arc<RwLock<TypeMap>>,
+	/// Songs that were queued before may be files, they are only opened inside of these.
+	roots: MediaRoots,
+}
+
+impl SongbirdBackend {
+	pub fn new(songbird: Arc<Songbird>, roots: MediaRoots) -> Self {
+		SongbirdBackend {
+			songbird,
+			players: Mutex::new(HashMap::new()),
+			roots,
+		}
+	}
+
+	/// The queue of the guild's call, `None` while the bot isn't connected.
+	async fn track_queue(&self, guild_id: GuildId) -> Option<TrackQueue> {
+		let call = self.songbird.get(guild_id)?;
+		let queue = call.lock().await.queue().clone();
+
+		Some(queue)
+	}
+
+	async fn player(&self, guild_id: GuildId) -> Option<Player> {
+		Some(Player {
+			songbird: Arc::clone(&self.songbird),
+			guild_id,
+			data: self.data(guild_id).await?,
+			roots: self.roots.clone(),
+		})
+	}
+}
+
+#[async_trait]
+impl MusicBackend for SongbirdBackend {
+	async fn is_available(&self, _guild_id: GuildId) -> bool {
+		true
+	}
+
+	async fn players(&self) -> Vec<GuildId> {
+		self.players.lock().await.keys().copied().collect()
+	}
+
+	async fn connect(
+		&self,
+		ctx: &Context,
+		guild_id: GuildId,
+		voice_channel_id: ChannelId,
+		text_channel_id: ChannelId,
+	) -> Result<(), ConnectError> {
+		if let Err(why) = self.songbird.join(guild_id, voice_channel_id).await.1 {
+			let _ = self.songbird.remove(guild_id).await;
+
+			return Err(ConnectError::Join(why));
+		}
+
+		let (database, backend) = {
+			let data = ctx.data.read().await;
+
+			(data.get::<Database>().unwrap().clone(), data.get::<Backend>().unwrap().clone())
+		};
+
+		let volume = database.guild_volume(guild_id).await.map_err(|why| ConnectError::Other(why.into()))?;
+
+		let mut data = TypeMap::new();
+		data.insert::<CallerChannel>(CallerChannel {
+			channel_id: text_channel_id,
+			http: ctx.http.clone(),
+		});
+		data.insert::<Volume>(volume.unwrap_or(DEFAULT_VOLUME));
+		// The events of the tracks don't get the context, they find the backend here
+		data.insert::<Backend>(backend);
+
+		self.players.lock().await.insert(guild_id, Arc::new(RwLock::new(data)));
+
+		Ok(())
+	}
+
+	async fn disconnect(&self, _ctx: &Context, guild_id: GuildId) -> Result<(), JoinError> {
+		if let Some(data) = self.players.lock().await.remove(&guild_id) {
+			panel::close(&data).await;
+		}
+
+		if let Some(queue) = self.track_queue(guild_id).await {
+			queue.stop();
+		}
+
+		self.songbird.remove(guild_id).await
+	}
+
+	async fn data(&self, guild_id: GuildId) -> Option<Arc<RwLock<TypeMap>>> {
+		self.players.lock().await.get(&guild_id).cloned()
+	}
+
+	async fn enqueue(&self, guild_id: GuildId, query: &str, requester: UserId, next: bool) -> CommandResult<usize> {
+		let (player, input) = match (self.player(guild_id).await, ytdl_source(query).await) {
+			(Some(player), Some(input)) => (player, input),
+			_ => return Ok(0),
+		};
+
+		// The first song is the one playing
+		let added = player.add(input, Some(requester), if next { Some(1) } else { None }).await;
+
+		Ok(usize::from(added))
+	}
+
+	async fn enqueue_file(&self, guild_id: GuildId, path: &Path, requester: UserId) -> CommandResult<usize> {
+		let path = match self.roots.resolve(path).await {
+			Some(path) => path,
+			None => return Ok(0),
+		};
+
+		let (player, input) = match (self.player(guild_id).await, file_source(&path).await) {
+			(Some(player), Some(input)) => (player, input),
+			_ => return Ok(0),
+		};
+
+		Ok(usize::from(player.add(input, Some(requester), None).await))
+	}
+
+	async fn enqueue_tracks(&self, guild_id: GuildId, tracks: &[QueuedTrack]) -> CommandResult<usize> {
+		let player = match self.player(guild_id).await {
+			Some(player) => player,
+			None => return Ok(0),
+		};
+
+		let mut added = 0;
+		for track in tracks {
+			if player.add_again(track, None).await {
+				added += 1;
+			}
+		}
+
+		Ok(added)
+	}
+
+	async fn resume(&self, guild_id: GuildId, tracks: &[QueuedTrack], position: Option<u64>, paused: bool) -> CommandResult {
+		self.enqueue_tracks(guild_id, tracks).await?;
+
+		if let Some(queue) = self.track_queue(guild_id).await {
+			if let (Some(current), Some(position)) = (queue.current(), position) {
+				current.seek_time(Duration::from_millis(position))?;
+			}
+
+			if paused {
+				queue.pause()?;
+			}
+		}
+
+		Ok(())
+	}
+
+	async fn play_clip(&self, guild_id: GuildId, path: &Path, _requester: UserId) -> CommandResult<ClipPlayback> {
+		let (call, data) = match (self.songbird.get(guild_id), self.data(guild_id).await) {
+			(Some(call), Some(data)) => (call, data),
+			_ => return Ok(ClipPlayback::Failed),
+		};
+
+		if data.read().await.contains_key::<PlayingClip>() {
+			return Ok(ClipPlayback::AlreadyPlaying);
+		}
+
+		let path = match self.roots.resolve(path).await {
+			Some(path) => path,
+			None => return Ok(ClipPlayback::Failed),
+		};
+
+		let source = match Restartable::ffmpeg(path.clone().into_os_string(), false).await {
+			Ok(source) => source,
+			Err(why) => {
+				log::warn!("ffmpeg could not open {}: {:?}", path.display(), why);
+
+				return Ok(ClipPlayback::Failed);
+			}
+		};
+
+		let queue = call.lock().await.queue().clone();
+
+		let paused = match queue.current() {
+			Some(current) => current.get_info().await.map_or(true, |state| state.playing == PlayMode::Pause),
+			None => true,
+		};
+
+		if !paused {
+			queue.pause()?;
+		}
+
+		let volume = data.read().await.get::<Volume>().copied().unwrap_or(DEFAULT_VOLUME);
+
+		// Played next to the queue instead of in it, so the queue stays where it was
+		let (mut track, handle) = create_player(source.into());
+		track.set_volume(volume as f32 / 100.0);
+
+		data.write().await.insert::<PlayingClip>(PlayingClip { track: handle.uuid().to_string(), paused });
+		call.lock().await.play(track);
+
+		handle.add_event(Event::Track(TrackEvent::End), ClipEnd { data, queue })?;
+
+		Ok(ClipPlayback::Started)
+	}
+
+	async fn replay(&self, guild_id: GuildId, track: &QueuedTrack) -> CommandResult<bool> {
+		let (player, queue) = match (self.player(guild_id).await, self.track_queue(guild_id).await) {
+			(Some(player), Some(queue)) => (player, queue),
+			_ => return Ok(false),
+		};
+
+		let current = match queue.current() {
+			Some(current) => current,
+			None => return Ok(player.add_again(track, None).await),
+		};
+
+		if !player.add_again(track, Some(1)).await {
+			return Ok(false);
+		}
+
+		// Played tracks can't be started again, it is looked up once more to start over
+		player.add_again(&queued_track(&current), Some(2)).await;
+
+		// Ends the current song, which starts the one that is played again
+		queue.skip()?;
+
+		Ok(true)
+	}
+
+	async fn now_playing(&self, guild_id: GuildId) -> Option<NowPlaying> {
+		let handle = self.track_queue(guild_id).await?.current()?;
+		let state = handle.get_info().await.ok()?;
+
+		Some(NowPlaying {
+			track: queued_track(&handle),
+			position: state.position.as_millis() as u64,
+			paused: state.playing == PlayMode::Pause,
+		})
+	}
+
+	async fn queue(&self, guild_id: GuildId) -> Vec<QueuedTrack> {
+		let handles = match self.track_queue(guild_id).await {
+			Some(queue) => queue.current_queue(),
+			None => return Vec::new(),
+		};
+
+		handles.iter().skip(1).map(queued_track).collect()
+	}
+
+	async fn skip(&self, guild_id: GuildId) -> CommandResult<Option<QueuedTrack>> {
+		let (player, queue) = match (self.player(guild_id).await, self.track_queue(guild_id).await) {
+			(Some(player), Some(queue)) => (player, queue),
+			_ => return Ok(None),
+		};
+
+		let track = match queue.current() {
+			Some(handle) => queued_track(&handle),
+			None => return Ok(None),
+		};
+
+		queue.skip()?;
+
+		player.data.write().await.remove::<SkipVotes>();
+
+		// Skipped songs stay part of the loop when the whole queue is looped
+		if loop_mode(&player.data).await == LoopMode::Queue {
+			player.add_again(&track, None).await;
+		}
+
+		Ok(Some(track))
+	}
+
+	async fn skip_to(&self, guild_id: GuildId, index: usize) -> CommandResult<Option<QueuedTrack>> {
+		let (player, queue) = match (self.player(guild_id).await, self.track_queue(guild_id).await) {
+			(Some(player), Some(queue)) => (player, queue),
+			_ => return Ok(None),
+		};
+
+		// The current song is at index 0
+		let skipped = queue.modify_queue(|queue| {
+			if index + 1 < queue.len() {
+				Some(queue.drain(1..index + 1).collect::<Vec<_>>())
+			} else {
+				None
+			}
+		});
+
+		let (skipped, current) = match (skipped, queue.current()) {
+			(Some(skipped), Some(current)) => (skipped, current),
+			_ => return Ok(None),
+		};
+
+		// Skipped songs stay part of the loop when the whole queue is looped
+		if loop_mode(&player.data).await == LoopMode::Queue {
+			player.add_again(&queued_track(&current), None).await;
+			queue.modify_queue(|queue| queue.extend(skipped));
+		} else {
+			// Queued tracks already belong to the driver, they would stay there paused forever
+			for track in skipped {
+				let _ = track.stop();
+			}
+		}
+
+		let next = match queue.current_queue().get(1) {
+			Some(next) => queued_track(next),
+			None => return Ok(None),
+		};
+
+		// Ends the current song, which starts the one that is now next
+		queue.skip()?;
+
+		Ok(Some(next))
+	}
+
+	async fn move_track(&self, guild_id: GuildId, from: usize, to: usize) -> CommandResult<Option<QueuedTrack>> {
+		let queue = match self.track_queue(guild_id).await {
+			Some(queue) => queue,
+			None => return Ok(None),
+		};
+
+		let moved = queue.modify_queue(|queue| {
+			// The current song is at index 0 and can't be moved
+			if from + 1 >= queue.len() || to + 1 >= queue.len() {
+				return None;
+			}
+
+			let track = queue.remove(from + 1)?;
+			let handle = track.handle();
+			queue.insert(to + 1, track);
+
+			Some(handle)
+		});
+
+		match moved {
+			Some(handle) => Ok(Some(queued_track(&handle))),
+			None => Ok(None),
+		}
+	}
+
+	async fn set_paused(&self, guild_id: GuildId, paused: bool) -> CommandResult {
+		if let Some(queue) = self.track_queue(guild_id).await {
+			if paused {
+				queue.pause()?;
+			} else {
+				queue.resume()?;
+			}
+		}
+
+		Ok(())
+	}
+
+	async fn clear(&self, guild_id: GuildId) -> CommandResult {
+		if let Some(queue) = self.track_queue(guild_id).await {
+			let removed = queue.modify_queue(|queue| {
+				if queue.len() > 1 {
+					queue.drain(1..).collect()
+				} else {
+					Vec::new()
+				}
+			});
+
+			// Queued tracks already belong to the driver, they would stay there paused forever
+			for track in removed {
+				let _ = track.stop();
+			}
+		}
+
+		Ok(())
+	}
+
+	async fn remove(&self, guild_id: GuildId, removal: &Removal) -> CommandResult<Option<Vec<(usize, QueuedTrack)>>> {
+		let queue = match self.track_queue(guild_id).await {
+			Some(queue) => queue,
+			None => return Ok(Some(Vec::new())),
+		};
+
+		let removed = queue.modify_queue(|queue| {
+			// The current song comes first
+			let songs = queue.iter().skip(1).map(|track| queued_track(&track.handle())).collect::<Vec<_>>();
+			let indexes = removal.indexes(&songs)?;
+
+			// Removing from the back keeps the remaining indexes valid
+			let mut removed = indexes
+				.into_iter()
+				.rev()
+				.filter_map(|index| Some((index, songs[index].clone(), queue.remove(index + 1)?)))
+				.collect::<Vec<_>>();
+
+			removed.reverse();
+
+			Some(removed)
+		});
+
+		let removed = match removed {
+			Some(removed) => removed,
+			None => return Ok(None),
+		};
+
+		Ok(Some(
+			removed
+				.into_iter()
+				.map(|(index, song, track)| {
+					// Queued tracks already belong to the driver, they would stay there paused forever
+					let _ = track.stop();
+
+					(index, song)
+				})
+				.collect()
+		))
+	}
+
+	async fn shuffle(&self, guild_id: GuildId) -> CommandResult {
+		if let Some(queue) = self.track_queue(guild_id).await {
+			queue.modify_queue(|queue| {
+				// The current song stays where it is
+				if queue.len() > 1 {
+					queue.make_contiguous()[1..].shuffle(&mut StdRng::from_entropy());
+				}
+			});
+		}
+
+		Ok(())
+	}
+
+	async fn search(&self, _guild_id: GuildId, query: &str, _limit: usize) -> CommandResult<Vec<QueuedTrack>> {
+		// youtube-dl is only asked for the first result, songbird can't read more
+		let metadata = match ytdl_source(query).await {
+			Some(input) => input.metadata,
+			None => return Ok(Vec::new()),
+		};
+
+		// The link finds the song again once it is added
+		let found = metadata.source_url.clone().map(|uri| describe(uri, &metadata, None, true));
+
+		Ok(found.into_iter().collect())
+	}
+
+	async fn set_loop_mode(&self, guild_id: GuildId, mode: LoopMode) {
+		if let Some(data) = self.data(guild_id).await {
+			data.write().await.insert::<LoopMode>(mode);
+		}
+
+		// Songbird loops the track by itself, looping the queue happens once a track ended
+		if let Some(current) = self.track_queue(guild_id).await.and_then(|queue| queue.current()) {
+			let _ = match mode {
+				LoopMode::Track => current.enable_loop(),
+				LoopMode::Off | LoopMode::Queue => current.disable_loop(),
+			};
+		}
+	}
+
+	async fn volume(&self, guild_id: GuildId) -> Option<u16> {
+		let data = self.data(guild_id).await?;
+		let volume = data.read().await.get::<Volume>().copied();
+
+		volume
+	}
+
+	async fn set_volume(&self, guild_id: GuildId, volume: u16) -> CommandResult {
+		if let Some(data) = self.data(guild_id).await {
+			data.write().await.insert::<Volume>(volume);
+		}
+
+		if let Some(queue) = self.track_queue(guild_id).await {
+			for handle in queue.current_queue() {
+				handle.set_volume(volume as f32 / 100.0)?;
+			}
+		}
+
+		Ok(())
+	}
+
+	async fn seek(&self, guild_id: GuildId, position: u64) -> CommandResult {
+		if let Some(current) = self.track_queue(guild_id).await.and_then(|queue| queue.current()) {
+			current.seek_time(Duration::from_millis(position))?;
+		}
+
+		Ok(())
+	}
+}
+
+impl Player {
+	/// Adds the song to the end of the queue, or at the index of the songbird queue if there is one.
+	/// Returns whether it was added.
+	async fn add(&self, input: Input, requester: Option<UserId>, index: Option<usize>) -> bool {
+		let call = match self.songbird.get(self.guild_id) {
+			Some(call) => call,
+			None => return false,
+		};
+
+		let volume = self.data.read().await.get::<Volume>().copied().unwrap_or(DEFAULT_VOLUME);
+
+		let (mut track, handle) = create_player(input);
+		track.set_volume(volume as f32 / 100.0);
+		if let Some(requester) = requester {
+			handle.typemap().write().await.insert::<Requester>(requester);
+		}
+
+		if let Err(why) = handle.add_event(Event::Track(TrackEvent::Play), TrackStart { player: self.clone() }) {
+			log::warn!("Could not watch a track of guild {}: {:?}", self.guild_id, why);
+		}
+
+		if let Err(why) = handle.add_event(Event::Track(TrackEvent::End), TrackEnd { player: self.clone() }) {
+			log::warn!("Could not watch a track of guild {}: {:?}", self.guild_id, why);
+		}
+
+		let started = {
+			let mut call = call.lock().await;
+			call.enqueue(track);
+
+			// The new track is the last one
+			if let Some(index) = index {
+				call.queue().modify_queue(|queue| {
+					if index + 1 < queue.len() {
+						let track = queue.pop_back().unwrap();
+						queue.insert(index, track);
+					}
+				});
+			}
+
+			call.queue().len() == 1
+		};
+
+		// Songbird only tells about tracks that continue after a pause, not about ones that start right away
+		if started {
+			self.started(&handle).await;
+		}
+
+		true
+	}
+
+	/// Adds a song that was in a queue before, see [`Player::add`].
+	async fn add_again(&self, track: &QueuedTrack, index: Option<usize>) -> bool {
+		match source(track, &self.roots).await {
+			Some(input) => self.add(input, track.requester, index).await,
+			None => false,
+		}
+	}
+
+	/// Shows the new song in the panel and loops it if the loop mode says so.
+	async fn started(&self, handle: &TrackHandle) {
+		if loop_mode(&self.data).await == LoopMode::Track {
+			let _ = handle.enable_loop();
+		}
+
+		let backend = self.data.read().await.get::<Backend>().cloned();
+		if let Some(backend) = backend {
+			panel::show(&*backend, self.guild_id).await;
+		}
+	}
+}
+
+/// Like `track_start` does for lavalink, for tracks of the queue that start after the one before them.
+struct TrackStart {
+	player: Player,
+}
+
+#[async_trait]
+impl VoiceEventHandler for TrackStart {
+	async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+		if let EventContext::Track([(_, handle)]) = ctx {
+			self.player.started(handle).await;
+		}
+
+		None
+	}
+}
+
+/// Cleans up after a track of the queue ended, like `track_finish` does for lavalink.
+struct TrackEnd {
+	player: Player,
+}
+
+#[async_trait]
+impl VoiceEventHandler for TrackEnd {
+	async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+		let (state, handle) = match ctx {
+			EventContext::Track([(state, handle)]) => (state, handle),
+			_ => return Some(Event::Cancel),
+		};
+
+		self.player.data.write().await.remove::<SkipVotes>();
+
+		// Removed before it was its turn
+		if state.play_time.is_zero() {
+			return Some(Event::Cancel);
+		}
+
+		let track = queued_track(handle);
+		self.player.data.write().await.entry::<History>().or_insert_with(History::default).push(track.clone());
+
+		// Skipped or stopped tracks are not looped, looped tracks don't end
+		if state.playing == PlayMode::End && loop_mode(&self.player.data).await == LoopMode::Queue {
+			let player = self.player.clone();
+
+			// youtube-dl takes a moment to find the song again, the other events shouldn't wait for it
+			tokio::spawn(async move {
+				player.add_again(&track, None).await;
+			});
+		}
+
+		Some(Event::Cancel)
+	}
+}
+
+/// Continues the queue once a soundboard clip is over.
+struct ClipEnd {
+	data: Arc<RwLock<TypeMap>>,
+	queue: TrackQueue,
+}
+
+#[async_trait]
+impl VoiceEventHandler for ClipEnd {
+	async fn act(&self, _ctx: &EventContext<'_>) -> Option<Event> {
+		if let Some(clip) = self.data.write().await.remove::<PlayingClip>() {
+			if !clip.paused {
+				if let Err(why) = self.queue.resume() {
+					log::warn!("Could not resume the queue after a soundboard clip: {:?}", why);
+				}
+			}
+		}
+
+		Some(Event::Cancel)
+	}
+}
+
+/// The songs of a link, or the first search result.
+/// Lazy sources only start downloading once it's their turn.
+async fn ytdl_source(query: &str) -> Option<Input> {
+	let source = if is_link(query) {
+		Restartable::ytdl(query.to_string(), true).await
+	} else {
+		Restartable::ytdl_search(query, true).await
+	};
+
+	match source {
+		Ok(source) => Some(source.into()),
+		Err(why) => {
+			log::warn!("youtube-dl could not find anything for {}: {:?}", query, why);
+
+			None
+		}
+	}
+}
+
+async fn file_source(path: &Path) -> Option<Input> {
+	let source = match Restartable::ffmpeg(path.to_path_buf().into_os_string(), true).await {
+		Ok(source) => source,
+		Err(why) => {
+			log::warn!("ffmpeg could not open {}: {:?}", path.display(), why);
+
+			return None;
+		}
+	};
+
+	let mut input: Input = source.into();
+
+	// Lazy sources don't read the tags of the file until they start
+	if input.metadata.title.is_none() {
+		input.metadata.title = path.file_stem().map(|name| name.to_string_lossy().into_owned());
+	}
+
+	// Finds the file again, like a link would
+	input.metadata.source_url = Some(path.to_string_lossy().into_owned());
+
+	Some(input)
+}
+
+/// Looks up a song that was in a queue before again. Played tracks are gone, songbird can't start them over.
+/// Songs come back from playlists, saved players and imports, so anything but a link has to be a file inside the roots.
+async fn source(track: &QueuedTrack, roots: &MediaRoots) -> Option<Input> {
+	match track.uri.as_deref() {
+		Some(uri) if is_link(uri) => ytdl_source(uri).await,
+		Some(path) => file_source(&roots.resolve(Path::new(path)).await?).await,
+		None => None,
+	}
+}
+
+/// The requester is set before the track is queued and never changes, so reading it doesn't have to wait.
+fn queued_track(handle: &TrackHandle) -> QueuedTrack {
+	let requester = handle.typemap().try_read().ok().and_then(|typemap| typemap.get::<Requester>().copied());
+
+	describe(handle.uuid().to_string(), handle.metadata(), requester, handle.is_seekable())
+}
+
+fn describe(id: String, metadata: &Metadata, requester: Option<UserId>, is_seekable: bool) -> QueuedTrack {
+	QueuedTrack {
+		id,
+		title: metadata.title.clone().unwrap_or_else(|| "Unknown".to_string()),
+		uri: metadata.source_url.clone(),
+		length: metadata.duration.map_or(0, |duration| duration.as_millis() as u64),
+		// youtube-dl doesn't know how long live streams are
+		is_stream: metadata.duration.is_none(),
+		is_seekable,
+		requester,
+	}
+}
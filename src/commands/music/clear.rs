@@ -3,20 +3,26 @@ use serenity::framework::standard::macros::command;
 use serenity::model::prelude::Message;
 use serenity::prelude::Context;
 
-use crate::commands::music::handlers::Lavalink;
+use crate::commands::music::dj::DJ_CHECK;
+use crate::commands::music::util::music_backend;
+use crate::invocation::Invocation;
 
 #[command]
+#[checks(DJ)]
 #[description("Clears the contents of the queue")]
 async fn clear(ctx: &Context, msg: &Message) -> CommandResult {
-	let data = ctx.data.read().await;
-	let lava_client = data.get::<Lavalink>().unwrap().clone();
-	let guild_id = u64::from(msg.guild_id.unwrap());
+	handle_clear(ctx, &msg.into()).await
+}
 
-	if let Some(mut node) = lava_client.nodes().await.get_mut(&guild_id) {
-		node.queue.truncate(1);
-		msg.channel_id.say(&ctx.http, "Cleared queue").await?;
+pub async fn handle_clear(ctx: &Context, invocation: &Invocation<'_>) -> CommandResult {
+	let backend = music_backend(ctx, invocation).await?;
+	let guild_id = invocation.guild_id().unwrap();
+
+	if backend.data(guild_id).await.is_some() {
+		backend.clear(guild_id).await?;
+		invocation.say(ctx, "Cleared queue").await?;
 	} else {
-		msg.reply(&ctx.http, "Not in a channel").await?;
+		invocation.reply(ctx, "Not in a channel").await?;
 	}
 
 
@@ -0,0 +1,214 @@
+use serenity::framework::standard::{Args, CommandOptions, CommandResult, Reason};
+use serenity::framework::standard::macros::{check, command};
+use serenity::model::prelude::{GuildId, Message, RoleId, UserId};
+use serenity::prelude::{Context, Mentionable};
+
+use crate::commands::music::backend::Backend;
+use crate::commands::music::remove::Removal;
+use crate::commands::music::util::listeners;
+use crate::config::ConfigData;
+use crate::database::Database;
+use crate::invocation::Invocation;
+
+/// Commands that change what everyone listens to. Message commands are
+/// guarded by `#[checks(DJ)]`, slash commands look them up here.
+pub const DJ_COMMANDS: &[&str] = &["clear", "remove", "shuffle", "leave", "skip", "skipto", "previous"];
+
+#[command]
+#[required_permissions("ADMINISTRATOR")]
+#[description("Shows or changes the DJ role of this server.\nWhile a DJ role is set, only DJs can use `clear`, `remove`, `shuffle`, `leave`, `skipto`, `previous` and skip right away. Use `reset` to let everyone use them again.")]
+#[usage("$role")]
+#[example("")]
+#[example("@DJ")]
+#[example("reset")]
+async fn djrole(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+	if args.is_empty() {
+		return handle_dj_role(ctx, &msg.into(), None).await;
+	}
+
+	if args.rest().eq_ignore_ascii_case("reset") {
+		return handle_dj_role(ctx, &msg.into(), Some(None)).await;
+	}
+
+	// Roles can be mentioned or given by their name
+	let role_id = match msg.mention_roles.first() {
+		Some(role_id) => Some(*role_id),
+		None => find_role(ctx, msg.guild_id.unwrap(), args.rest()).await,
+	};
+
+	match role_id {
+		Some(role_id) => handle_dj_role(ctx, &msg.into(), Some(Some(role_id))).await,
+		None => {
+			msg.reply(&ctx.http, "Unknown role, mention it or use its name").await?;
+
+			Ok(())
+		}
+	}
+}
+
+/// `None` shows the DJ role, `Some(None)` removes it.
+pub async fn handle_dj_role(ctx: &Context, invocation: &Invocation<'_>, role_id: Option<Option<RoleId>>) -> CommandResult {
+	let guild_id = invocation.guild_id().unwrap();
+	let database = ctx.data.read().await.get::<Database>().unwrap().clone();
+
+	let role_id = match role_id {
+		Some(role_id) => role_id,
+		None => {
+			match database.guild_dj_role(guild_id).await? {
+				Some(role_id) => invocation.say(ctx, format!("The DJ role is {}", role_id.mention())).await?,
+				None => invocation.say(ctx, "There is no DJ role, everyone can control the music").await?,
+			};
+
+			return Ok(());
+		}
+	};
+
+	// Slash commands skip the framework, so `required_permissions` has to be checked here as well
+	if !is_admin(ctx, guild_id, invocation.author().id).await {
+		invocation.reply(ctx, "Only administrators can change the DJ role").await?;
+
+		return Ok(());
+	}
+
+	database.set_guild_dj_role(guild_id, role_id).await?;
+
+	match role_id {
+		Some(role_id) => invocation.say(ctx, format!("Set the DJ role to {}", role_id.mention())).await?,
+		None => invocation.say(ctx, "Removed the DJ role, everyone can control the music again").await?,
+	};
+
+	Ok(())
+}
+
+#[check]
+#[name = "DJ"]
+async fn dj_check(ctx: &Context, msg: &Message, args: &mut Args, options: &CommandOptions) -> Result<(), Reason> {
+	let guild_id = match msg.guild_id {
+		Some(guild_id) => guild_id,
+		None => return Ok(()),
+	};
+
+	let removal = if options.names.contains(&"remove") {
+		args.rest().parse().ok()
+	} else {
+		None
+	};
+
+	check_dj(ctx, guild_id, msg.author.id, options.names[0], removal.as_ref()).await.map_err(Reason::User)
+}
+
+/// Whether the user may run one of the `DJ_COMMANDS`, the error is the reason why not.
+///
+/// Without a DJ role everyone may. Otherwise it's DJs, people alone with the bot,
+/// people removing only songs they requested themselves and everyone voting to skip.
+pub async fn check_dj(
+	ctx: &Context,
+	guild_id: GuildId,
+	user_id: UserId,
+	command: &str,
+	removal: Option<&Removal>,
+) -> Result<(), String> {
+	let dj_role = match dj_role(ctx, guild_id).await {
+		Some(dj_role) => dj_role,
+		None => return Ok(()),
+	};
+
+	if is_dj(ctx, guild_id, user_id).await {
+		return Ok(());
+	}
+
+	// Nobody is listening either when the guild isn't cached, so the user has to be there
+	if listeners(ctx, guild_id).await == [user_id] {
+		return Ok(());
+	}
+
+	// Everyone can vote, only DJs and whoever requested the song skip right away
+	if command == "skip" && vote_skipping(ctx).await {
+		return Ok(());
+	}
+
+	if let Some(removal) = removal {
+		if queued_only_by(ctx, guild_id, user_id, removal).await {
+			return Ok(());
+		}
+	}
+
+	Err(format!("Only people with the {} role can do that while others are listening", dj_role.mention()))
+}
+
+/// Whether the user may change music settings that belong to the whole server, like its playlists.
+/// Without a DJ role everyone may.
+pub async fn may_manage(ctx: &Context, guild_id: GuildId, user_id: UserId) -> bool {
+	dj_role(ctx, guild_id).await.is_none() || is_dj(ctx, guild_id, user_id).await
+}
+
+/// Whether the user may control the player without asking anyone else.
+/// That's administrators and everyone with the DJ role of the guild.
+pub async fn is_dj(ctx: &Context, guild_id: GuildId, user_id: UserId) -> bool {
+	if is_admin(ctx, guild_id, user_id).await {
+		return true;
+	}
+
+	let dj_role = match dj_role(ctx, guild_id).await {
+		Some(dj_role) => dj_role,
+		None => return false,
+	};
+
+	match guild_id.to_guild_cached(&ctx.cache).await.and_then(|guild| guild.members.get(&user_id).cloned()) {
+		Some(member) => member.roles.contains(&dj_role),
+		None => false,
+	}
+}
+
+/// Errors are treated like no DJ role being set, so music keeps working.
+async fn dj_role(ctx: &Context, guild_id: GuildId) -> Option<RoleId> {
+	let database = ctx.data.read().await.get::<Database>().unwrap().clone();
+
+	match database.guild_dj_role(guild_id).await {
+		Ok(dj_role) => dj_role,
+		Err(why) => {
+			log::error!("Error reading the DJ role of guild {}: {:?}", guild_id, why);
+
+			None
+		}
+	}
+}
+
+async fn is_admin(ctx: &Context, guild_id: GuildId, user_id: UserId) -> bool {
+	let guild = match guild_id.to_guild_cached(&ctx.cache).await {
+		Some(guild) => guild,
+		None => return false,
+	};
+
+	match guild.member_permissions(ctx, user_id).await {
+		Ok(permissions) => permissions.administrator(),
+		Err(_) => false,
+	}
+}
+
+async fn find_role(ctx: &Context, guild_id: GuildId, name: &str) -> Option<RoleId> {
+	let guild = guild_id.to_guild_cached(&ctx.cache).await?;
+
+	guild.roles
+		.values()
+		.find(|role| role.name.eq_ignore_ascii_case(name))
+		.map(|role| role.id)
+}
+
+async fn vote_skipping(ctx: &Context) -> bool {
+	ctx.data.read().await
+		.get::<ConfigData>().unwrap()
+		.music.as_ref()
+		.is_some_and(|music| music.vote_skip_percentage > 0)
+}
+
+/// Whether every song the removal would remove was requested by the user.
+async fn queued_only_by(ctx: &Context, guild_id: GuildId, user_id: UserId, removal: &Removal) -> bool {
+	let backend = ctx.data.read().await.get::<Backend>().unwrap().clone();
+	let queue = backend.queue(guild_id).await;
+
+	match removal.indexes(&queue) {
+		Some(indexes) => indexes.iter().all(|index| queue[*index].requester == Some(user_id)),
+		None => false,
+	}
+}
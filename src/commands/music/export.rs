@@ -0,0 +1,246 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use serenity::framework::standard::CommandResult;
+use serenity::model::prelude::Attachment;
+use serenity::prelude::Context;
+
+use crate::commands::music::util::{is_link, music_backend};
+use crate::invocation::Invocation;
+
+/// Larger files are most likely not a queue.
+const MAX_IMPORT_SIZE: u64 = 1024 * 1024;
+const MAX_IMPORT_TRACKS: usize = 500;
+
+#[derive(Clone, Copy)]
+pub enum ExportFormat {
+	Json,
+	M3u,
+}
+
+impl FromStr for ExportFormat {
+	type Err = ();
+
+	fn from_str(format: &str) -> Result<Self, Self::Err> {
+		match format.to_lowercase().as_str() {
+			"json" => Ok(ExportFormat::Json),
+			"m3u" | "m3u8" => Ok(ExportFormat::M3u),
+			_ => Err(()),
+		}
+	}
+}
+
+/// A song of an exported queue.
+#[derive(Serialize, Deserialize)]
+struct ExportedTrack {
+	title: String,
+	uri: String,
+	/// In milliseconds.
+	length: u64,
+	/// A string, because JSON numbers can't hold user IDs in every language.
+	requester: Option<String>,
+}
+
+pub async fn handle_export(ctx: &Context, invocation: &Invocation<'_>, format: ExportFormat) -> CommandResult {
+	let backend = music_backend(ctx, invocation).await?;
+	let guild_id = invocation.guild_id().unwrap();
+
+	let current = backend.now_playing(guild_id).await.map(|now_playing| now_playing.track);
+
+	let tracks = current
+		.into_iter()
+		.chain(backend.queue(guild_id).await)
+		.filter_map(|track| Some(ExportedTrack {
+			title: track.title,
+			uri: track.uri?,
+			length: track.length,
+			requester: track.requester.map(|requester| requester.to_string()),
+		}))
+		.collect::<Vec<_>>();
+
+	if tracks.is_empty() {
+		invocation.say(ctx, "Queue is empty").await?;
+
+		return Ok(());
+	}
+
+	let (filename, data) = match format {
+		ExportFormat::Json => ("queue.json", serde_json::to_vec_pretty(&tracks)?),
+		ExportFormat::M3u => ("queue.m3u", to_m3u(&tracks).into_bytes()),
+	};
+
+	invocation.send_file(ctx, format!("Exported {} songs", tracks.len()), filename, data).await?;
+
+	Ok(())
+}
+
+pub async fn handle_import(ctx: &Context, invocation: &Invocation<'_>, attachment: &Attachment) -> CommandResult {
+	let guild_id = invocation.guild_id().unwrap();
+
+	let manager = songbird::get(ctx).await.unwrap().clone();
+	if manager.get(guild_id).is_none() {
+		invocation.say(ctx, "Use `join` first, to connect the bot to your current voice channel.").await?;
+
+		return Ok(());
+	}
+
+	if attachment.size > MAX_IMPORT_SIZE {
+		invocation.reply(ctx, "The file is too large").await?;
+
+		return Ok(());
+	}
+
+	let content = String::from_utf8_lossy(&attachment.download().await?).into_owned();
+
+	let entries = if attachment.filename.to_lowercase().ends_with(".json") || content.trim_start().starts_with('[') {
+		match serde_json::from_str::<Vec<ExportedTrack>>(&content) {
+			Ok(tracks) => tracks.into_iter().map(|track| (track.title, track.uri)).collect(),
+			Err(why) => {
+				invocation.reply(ctx, format!("Could not read the file: {}", why)).await?;
+
+				return Ok(());
+			}
+		}
+	} else {
+		from_m3u(&content)
+	};
+
+	if entries.is_empty() {
+		invocation.reply(ctx, "The file doesn't contain any songs").await?;
+
+		return Ok(());
+	}
+
+	if entries.len() > MAX_IMPORT_TRACKS {
+		invocation.reply(ctx, format!("Only up to {} songs can be imported at once", MAX_IMPORT_TRACKS)).await?;
+
+		return Ok(());
+	}
+
+	let backend = music_backend(ctx, invocation).await?;
+
+	let mut added = 0;
+	let mut failed = Vec::new();
+
+	for (title, uri) in entries {
+		// Files can hold anything, only links are played and nothing is searched for
+		if !is_link(&uri) {
+			failed.push(title);
+			continue;
+		}
+
+		match backend.enqueue(guild_id, &uri, invocation.author().id, false).await {
+			Ok(0) => failed.push(title),
+			Ok(count) => added += count,
+			Err(why) => {
+				log::warn!("Could not resolve {} while importing: {:?}", uri, why);
+				failed.push(title);
+			}
+		}
+	}
+
+	if failed.is_empty() {
+		invocation.say(ctx, format!("Imported {} songs", added)).await?;
+	} else {
+		let failed = failed.iter().map(|title| format!("- {}", title)).collect::<Vec<_>>().join("\n");
+
+		invocation.send_embed(ctx, |embed| {
+			embed.title(format!("Imported {} songs", added))
+				// Embed field values can be at most 1024 characters long
+				.field("Could not be found", truncate(&failed, 1024), false)
+		}).await?;
+	}
+
+	Ok(())
+}
+
+fn to_m3u(tracks: &[ExportedTrack]) -> String {
+	let mut m3u = String::from("#EXTM3U\n");
+
+	for track in tracks {
+		m3u.push_str(&format!("#EXTINF:{},{}\n", track.length / 1000, track.title));
+		if let Some(requester) = &track.requester {
+			m3u.push_str(&format!("#EXTREQUESTER:{}\n", requester));
+		}
+		m3u.push_str(&format!("{}\n", track.uri));
+	}
+
+	m3u
+}
+
+/// The titles (or links, if there is no `#EXTINF`) and links of a M3U playlist.
+fn from_m3u(content: &str) -> Vec<(String, String)> {
+	let mut entries = Vec::new();
+	let mut title = None;
+
+	for line in content.lines().map(str::trim).filter(|line| !line.is_empty()) {
+		if let Some(info) = line.strip_prefix("#EXTINF:") {
+			title = info.split_once(',').map(|(_, title)| title.to_string());
+		} else if !line.starts_with('#') {
+			entries.push((title.take().unwrap_or_else(|| line.to_string()), line.to_string()));
+		}
+	}
+
+	entries
+}
+
+/// Shortens the text to at most `max_length` bytes, marking the cut with `...`.
+fn truncate(text: &str, max_length: usize) -> String {
+	if text.len() <= max_length {
+		return text.to_string();
+	}
+
+	let mut end = max_length - 3;
+	while !text.is_char_boundary(end) {
+		end -= 1;
+	}
+
+	format!("{}...", &text[..end])
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn m3u_round_trip_keeps_titles_and_links() {
+		let tracks = [
+			ExportedTrack {
+				title: "Never Gonna Give You Up".to_string(),
+				uri: "https://www.youtube.com/watch?v=dQw4w9WgXcQ".to_string(),
+				length: 213_000,
+				requester: Some("123".to_string()),
+			},
+			ExportedTrack {
+				title: "Artist, Song".to_string(),
+				uri: "https://soundcloud.com/artist/song".to_string(),
+				length: 0,
+				requester: None,
+			},
+		];
+
+		let m3u = to_m3u(&tracks);
+
+		assert!(m3u.contains("#EXTINF:213,Never Gonna Give You Up\n#EXTREQUESTER:123\n"));
+		assert_eq!(from_m3u(&m3u), vec![
+			("Never Gonna Give You Up".to_string(), "https://www.youtube.com/watch?v=dQw4w9WgXcQ".to_string()),
+			("Artist, Song".to_string(), "https://soundcloud.com/artist/song".to_string()),
+		]);
+	}
+
+	#[test]
+	fn m3u_without_extinf_uses_links_as_titles() {
+		let m3u = "https://example.com/a.mp3\r\n\r\n#EXTVLCOPT:network-caching=1000\n  https://example.com/b.mp3  \n";
+
+		assert_eq!(from_m3u(m3u), vec![
+			("https://example.com/a.mp3".to_string(), "https://example.com/a.mp3".to_string()),
+			("https://example.com/b.mp3".to_string(), "https://example.com/b.mp3".to_string()),
+		]);
+	}
+
+	#[test]
+	fn truncates_on_char_boundaries() {
+		assert_eq!(truncate("short", 10), "short");
+		assert_eq!(truncate("äääää", 8), "ää...");
+	}
+}
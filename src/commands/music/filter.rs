@@ -0,0 +1,301 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use async_tungstenite::tungstenite::Message as WebsocketMessage;
+use futures::SinkExt;
+use lavalink_rs::LavalinkClient;
+use lavalink_rs::model::LavalinkResult;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use serenity::framework::standard::{Args, CommandResult};
+use serenity::framework::standard::macros::command;
+use serenity::model::prelude::{GuildId, Message};
+use serenity::prelude::{Context, TypeMapKey};
+
+use crate::commands::music::util::lavalink_client;
+use crate::invocation::Invocation;
+
+pub const EQ_BANDS: usize = 15;
+pub const MIN_GAIN: f64 = -0.25;
+pub const MAX_GAIN: f64 = 1.0;
+
+/// Gains of the lower equalizer bands, added on top of the bands set with `filter eq`.
+const BASSBOOST: [f64; 5] = [0.2, 0.15, 0.1, 0.05, 0.0];
+
+const USAGE: &str = "Use one of `bassboost`, `nightcore`, `vaporwave`, `8d`, `karaoke`, `eq <band> <gain>` or `reset`";
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Preset {
+	Bassboost,
+	Nightcore,
+	Vaporwave,
+	Rotation,
+	Karaoke,
+}
+
+impl Preset {
+	/// Presets that change the same filter can't be active at the same time.
+	fn conflicts_with(self, other: Preset) -> bool {
+		matches!(
+			(self, other),
+			(Preset::Nightcore, Preset::Vaporwave) | (Preset::Vaporwave, Preset::Nightcore)
+		)
+	}
+}
+
+impl Display for Preset {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Preset::Bassboost => write!(f, "bassboost"),
+			Preset::Nightcore => write!(f, "nightcore"),
+			Preset::Vaporwave => write!(f, "vaporwave"),
+			Preset::Rotation => write!(f, "8d"),
+			Preset::Karaoke => write!(f, "karaoke"),
+		}
+	}
+}
+
+impl FromStr for Preset {
+	type Err = ();
+
+	fn from_str(name: &str) -> Result<Self, Self::Err> {
+		match name.to_lowercase().as_str() {
+			"bassboost" | "bass" => Ok(Preset::Bassboost),
+			"nightcore" => Ok(Preset::Nightcore),
+			"vaporwave" => Ok(Preset::Vaporwave),
+			"8d" | "rotation" => Ok(Preset::Rotation),
+			"karaoke" => Ok(Preset::Karaoke),
+			_ => Err(()),
+		}
+	}
+}
+
+pub enum FilterChange {
+	/// Turns a preset on, or off if it is already on.
+	Toggle(Preset),
+	/// Sets the gain of a single equalizer band.
+	Band(usize, f64),
+	Reset,
+}
+
+/// The filters that are active on the player of a guild.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Filters {
+	presets: Vec<Preset>,
+	bands: [f64; EQ_BANDS],
+}
+
+impl TypeMapKey for Filters {
+	type Value = Filters;
+}
+
+impl Filters {
+	/// Returns whether the preset is on afterwards.
+	fn toggle(&mut self, preset: Preset) -> bool {
+		if self.presets.contains(&preset) {
+			self.presets.retain(|active| *active != preset);
+
+			return false;
+		}
+
+		self.presets.retain(|active| !active.conflicts_with(preset));
+		self.presets.push(preset);
+
+		true
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.presets.is_empty() && self.bands.iter().all(|gain| *gain == 0.0)
+	}
+
+	/// The `filters` op of lavalink, filters that are left out get turned off.
+	fn payload(&self, guild_id: GuildId) -> Value {
+		let mut payload = Map::new();
+		payload.insert("op".to_string(), json!("filters"));
+		payload.insert("guildId".to_string(), json!(guild_id.0.to_string()));
+
+		let mut bands = self.bands;
+		if self.presets.contains(&Preset::Bassboost) {
+			for (band, gain) in BASSBOOST.iter().enumerate() {
+				bands[band] = (bands[band] + gain).min(MAX_GAIN);
+			}
+		}
+
+		let equalizer = bands
+			.iter()
+			.enumerate()
+			.filter(|(_, gain)| **gain != 0.0)
+			.map(|(band, gain)| json!({ "band": band, "gain": gain }))
+			.collect::<Vec<_>>();
+
+		if !equalizer.is_empty() {
+			payload.insert("equalizer".to_string(), Value::Array(equalizer));
+		}
+
+		for preset in &self.presets {
+			match preset {
+				Preset::Bassboost => {}
+				Preset::Nightcore => {
+					payload.insert("timescale".to_string(), json!({ "speed": 1.2, "pitch": 1.2, "rate": 1.0 }));
+				}
+				Preset::Vaporwave => {
+					payload.insert("timescale".to_string(), json!({ "speed": 0.85, "pitch": 0.8, "rate": 1.0 }));
+				}
+				Preset::Rotation => {
+					payload.insert("rotation".to_string(), json!({ "rotationHz": 0.2 }));
+				}
+				Preset::Karaoke => {
+					payload.insert(
+						"karaoke".to_string(),
+						json!({ "level": 1.0, "monoLevel": 1.0, "filterBand": 220.0, "filterWidth": 100.0 }),
+					);
+				}
+			}
+		}
+
+		Value::Object(payload)
+	}
+}
+
+impl Display for Filters {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		if self.is_empty() {
+			return write!(f, "none");
+		}
+
+		let mut names = self.presets.iter().map(|preset| preset.to_string()).collect::<Vec<_>>();
+
+		let bands = self.bands
+			.iter()
+			.enumerate()
+			.filter(|(_, gain)| **gain != 0.0)
+			.map(|(band, gain)| format!("{}: {:+.2}", band, gain))
+			.collect::<Vec<_>>();
+
+		if !bands.is_empty() {
+			names.push(format!("eq ({})", bands.join(", ")));
+		}
+
+		write!(f, "{}", names.join(", "))
+	}
+}
+
+#[command]
+#[aliases("filters", "effect")]
+#[description("Shows or changes the audio filters of the player.\nPresets are `bassboost`, `nightcore`, `vaporwave`, `8d` and `karaoke`, using one again turns it off. `eq <band> <gain>` sets one of the 15 equalizer bands (0 to 14) to a gain from -0.25 to 1.0, `reset` turns every filter off.")]
+#[usage("$preset")]
+#[example("")]
+#[example("nightcore")]
+#[example("eq 0 0.3")]
+#[example("reset")]
+#[max_args(3)]
+async fn filter(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+	if args.is_empty() {
+		return handle_filter(ctx, &msg.into(), None).await;
+	}
+
+	let name = args.single::<String>()?;
+
+	let change = match name.to_lowercase().as_str() {
+		"reset" | "off" | "clear" => FilterChange::Reset,
+		"eq" | "equalizer" => match (args.single::<usize>(), args.single::<f64>()) {
+			(Ok(band), Ok(gain)) => FilterChange::Band(band, gain),
+			_ => {
+				msg.reply(&ctx.http, "Use `eq <band> <gain>`, for example `eq 0 0.3`").await?;
+
+				return Ok(());
+			}
+		},
+		name => match name.parse() {
+			Ok(preset) => FilterChange::Toggle(preset),
+			Err(()) => {
+				msg.reply(&ctx.http, format!("Unknown filter. {}", USAGE)).await?;
+
+				return Ok(());
+			}
+		},
+	};
+
+	handle_filter(ctx, &msg.into(), Some(change)).await
+}
+
+pub async fn handle_filter(ctx: &Context, invocation: &Invocation<'_>, change: Option<FilterChange>) -> CommandResult {
+	let lava_client = lavalink_client(ctx, invocation).await?;
+	let guild_id = invocation.guild_id().unwrap();
+
+	let data = match lava_client.nodes().await.get(&guild_id.0) {
+		Some(node) => Arc::clone(&node.data),
+		None => {
+			invocation.reply(ctx, "Not in a channel").await?;
+
+			return Ok(());
+		}
+	};
+
+	let change = match change {
+		Some(change) => change,
+		None => {
+			let filters = data.read().await.get::<Filters>().cloned().unwrap_or_default();
+			invocation.say(ctx, format!("Active filters: {}\n{}", filters, USAGE)).await?;
+
+			return Ok(());
+		}
+	};
+
+	if let FilterChange::Band(band, gain) = change {
+		if band >= EQ_BANDS {
+			invocation.reply(ctx, format!("The band has to be from 0 to {}", EQ_BANDS - 1)).await?;
+
+			return Ok(());
+		}
+
+		if !(MIN_GAIN..=MAX_GAIN).contains(&gain) {
+			invocation.reply(ctx, format!("The gain has to be from {} to {}", MIN_GAIN, MAX_GAIN)).await?;
+
+			return Ok(());
+		}
+	}
+
+	// The player data is only locked while changing the filters, not while talking to lavalink or discord
+	let (filters, response) = {
+		let mut data = data.write().await;
+		let filters = data.entry::<Filters>().or_insert_with(Filters::default);
+
+		let response = match change {
+			FilterChange::Toggle(preset) => {
+				if filters.toggle(preset) {
+					format!("Turned on {}", preset)
+				} else {
+					format!("Turned off {}", preset)
+				}
+			}
+			FilterChange::Band(band, gain) => {
+				filters.bands[band] = gain;
+				format!("Set equalizer band {} to {:+.2}", band, gain)
+			}
+			FilterChange::Reset => {
+				*filters = Filters::default();
+				"Turned off all filters".to_string()
+			}
+		};
+
+		(filters.clone(), response)
+	};
+
+	apply_filters(&lava_client, guild_id, &filters).await?;
+	invocation.say(ctx, response).await?;
+
+	Ok(())
+}
+
+/// lavalink-rs only knows the equalizer, so the `filters` op is sent directly.
+/// Needs lavalink 3.4 or newer.
+pub async fn apply_filters(lava_client: &LavalinkClient, guild_id: GuildId, filters: &Filters) -> LavalinkResult<()> {
+	let payload = filters.payload(guild_id).to_string();
+	let mut client = lava_client.inner.lock().await;
+
+	client.socket_write.send(WebsocketMessage::Text(payload)).await?;
+
+	Ok(())
+}
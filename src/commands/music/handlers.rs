@@ -2,44 +2,125 @@ use std::sync::Arc;
 
 use lavalink_rs::gateway::LavalinkEventHandler;
 use lavalink_rs::LavalinkClient;
-use lavalink_rs::model::{TrackFinish, TrackStart};
+use lavalink_rs::model::{Stats, TrackFinish, TrackStart};
 use serenity::async_trait;
 use serenity::http::Http;
-use serenity::model::prelude::ChannelId;
-use serenity::prelude::TypeMapKey;
+use serenity::model::prelude::{ChannelId, GuildId};
+use serenity::prelude::{RwLock, TypeMapKey};
 use songbird::{
 	Event,
 	EventContext,
 	EventHandler as VoiceEventHandler,
 };
 
-use crate::commands::music::util::CallerChannel;
+use crate::commands::music::backend::Backend;
+use crate::commands::music::backend::lavalink::queued_track;
+use crate::commands::music::history::History;
+use crate::commands::music::nodes::{LavalinkNodes, ResumePaused};
+use crate::commands::music::panel;
+use crate::commands::music::skip::SkipVotes;
+use crate::commands::music::soundboard::PlayingClip;
+use crate::commands::music::util::{loop_mode, LoopMode, PlayingTrack};
 
 #[async_trait]
 impl LavalinkEventHandler for LavalinkHandler {
+	async fn stats(&self, _client: LavalinkClient, event: Stats) {
+		*self.stats.write().await = Some(event);
+	}
+
 	async fn track_start(&self, client: LavalinkClient, event: TrackStart) {
-		//Use the data inside the Node Struct to store a Channel ID in the data Typemap field
-
-		if let Some(node) = client.nodes().await.get(&event.guild_id) {
-			if let Some(current_track) = &node.now_playing {
-				let typemap = node.data.read().await;
-				let caller_channel = typemap.get::<CallerChannel>().unwrap();
-				let _ = caller_channel.channel_id.send_message(&caller_channel.http, |message| {
-					message.embed(|embed| {
-						embed.title("**Now playing**");
-						embed.description(format!("{}", current_track.track.info.as_ref().unwrap().title));
-						embed
-					})
-				},
-				).await;
+		let current = client.nodes().await.get(&event.guild_id).and_then(|node| {
+			node.now_playing.clone().map(|current_track| (Arc::clone(&node.data), current_track))
+		});
+
+		if let Some((data, current_track)) = current {
+			let resume_paused = {
+				let mut data = data.write().await;
+				data.insert::<PlayingTrack>(current_track);
+				data.remove::<SkipVotes>();
+
+				data.remove::<ResumePaused>().is_some()
+			};
+
+			if resume_paused {
+				if let Err(why) = client.pause(event.guild_id).await {
+					log::error!("Could not pause the player of guild {}: {:?}", event.guild_id, why);
+				}
+			}
+
+			let backend = data.read().await.get::<Backend>().cloned();
+			if let Some(backend) = backend {
+				panel::show(&*backend, GuildId(event.guild_id)).await;
 			}
 		}
 
 		log::info!("A track in guild {} was started!", event.guild_id)
 	}
 
-	async fn track_finish(&self, _client: LavalinkClient, event: TrackFinish) {
+	async fn track_finish(&self, client: LavalinkClient, event: TrackFinish) {
 		log::info!("Track in guild {} finished with reason {}", event.guild_id, event.reason);
+
+		let data = match client.nodes().await.get(&event.guild_id) {
+			Some(node) => Arc::clone(&node.data),
+			None => return,
+		};
+
+		data.write().await.remove::<SkipVotes>();
+
+		// Soundboard clips only interrupt the queue, they aren't part of it
+		let clip = data.read().await.get::<PlayingClip>().map(|clip| (clip.track == event.track, clip.paused));
+		if let Some((true, paused)) = clip {
+			data.write().await.remove::<PlayingClip>();
+
+			if paused {
+				if let Err(why) = client.pause(event.guild_id).await {
+					log::error!("Could not pause the player of guild {} again: {:?}", event.guild_id, why);
+				}
+
+				if let Some(mut node) = client.nodes().await.get_mut(&event.guild_id) {
+					node.is_paused = true;
+				}
+			}
+
+			return;
+		}
+
+		let playing_track = data.read().await.get::<PlayingTrack>().cloned();
+
+		let mut track = match playing_track {
+			Some(track) if track.track.track == event.track => track,
+			_ => return,
+		};
+		track.start_time = 0;
+
+		// Failed tracks were never heard
+		if event.reason != "LOAD_FAILED" {
+			data.write().await.entry::<History>().or_insert_with(History::default).push(queued_track(&track));
+		}
+
+		// Skipped, stopped or failed tracks are not looped
+		if event.reason != "FINISHED" {
+			return;
+		}
+
+		let loop_mode = loop_mode(&data).await;
+
+		if loop_mode == LoopMode::Off {
+			return;
+		}
+
+		if let Some(mut node) = client.nodes().await.get_mut(&event.guild_id) {
+			match loop_mode {
+				LoopMode::Off => {}
+				LoopMode::Track => {
+					// The queue loop of lavalink-rs may have started the next track in the meantime
+					let index = if node.now_playing.is_some() { 1 } else { 0 };
+					let index = index.min(node.queue.len());
+					node.queue.insert(index, track);
+				}
+				LoopMode::Queue => node.queue.push(track),
+			}
+		}
 	}
 }
 
@@ -51,10 +132,14 @@ pub struct TrackEndNotifier {
 pub struct Lavalink;
 
 impl TypeMapKey for Lavalink {
-	type Value = LavalinkClient;
+	type Value = Arc<LavalinkNodes>;
 }
 
-pub(crate) struct LavalinkHandler;
+/// Every lavalink node gets its own handler.
+pub(crate) struct LavalinkHandler {
+	/// The latest stats of the node, used to balance guilds between nodes.
+	pub(crate) stats: Arc<RwLock<Option<Stats>>>,
+}
 
 #[async_trait]
 impl VoiceEventHandler for TrackEndNotifier {
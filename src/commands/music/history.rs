@@ -0,0 +1,154 @@
+use std::collections::VecDeque;
+
+use serenity::framework::standard::{Args, CommandResult};
+use serenity::framework::standard::macros::command;
+use serenity::model::prelude::Message;
+use serenity::prelude::{Context, Mentionable, TypeMapKey};
+
+use crate::commands::music::dj::DJ_CHECK;
+use crate::commands::music::backend::QueuedTrack;
+use crate::commands::music::util::music_backend;
+use crate::invocation::Invocation;
+
+/// How many played tracks are remembered per guild.
+const MAX_HISTORY: usize = 50;
+const PAGE_SIZE: usize = 15;
+
+/// The tracks that were played in a guild, the most recent first.
+#[derive(Default)]
+pub struct History {
+	tracks: VecDeque<QueuedTrack>,
+	/// A track that is about to be replaced by `previous` and played again afterwards,
+	/// so it shouldn't show up as played yet.
+	ignore: Option<String>,
+}
+
+impl TypeMapKey for History {
+	type Value = History;
+}
+
+impl History {
+	pub fn push(&mut self, track: QueuedTrack) {
+		if self.ignore.as_ref() == Some(&track.id) {
+			self.ignore = None;
+
+			return;
+		}
+
+		self.tracks.push_front(track);
+		self.tracks.truncate(MAX_HISTORY);
+	}
+}
+
+#[command]
+#[aliases("played")]
+#[description("Displays the songs that were played recently, the most recent first. Split up into pages of 15 songs per page.")]
+#[usage("$page")]
+#[example("")]
+#[example("2")]
+async fn history(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+	let page = if args.is_empty() {
+		1
+	} else {
+		args.single::<usize>()?.max(1)
+	};
+
+	handle_history(ctx, &msg.into(), page).await
+}
+
+#[command]
+#[checks(DJ)]
+#[aliases("back")]
+#[description("Plays the previous song again.\nThe current song continues afterwards.")]
+async fn previous(ctx: &Context, msg: &Message) -> CommandResult {
+	handle_previous(ctx, &msg.into()).await
+}
+
+pub async fn handle_history(ctx: &Context, invocation: &Invocation<'_>, page: usize) -> CommandResult {
+	let backend = music_backend(ctx, invocation).await?;
+	let data = backend.data(invocation.guild_id().unwrap()).await;
+
+	let mut page_content = String::new();
+
+	if let Some(data) = data {
+		if let Some(history) = data.read().await.get::<History>() {
+			for (index, track) in history.tracks.iter().enumerate().skip(PAGE_SIZE * (page - 1)).take(PAGE_SIZE) {
+				page_content.push_str(&format!("{} . {}", index + 1, track.title));
+				if let Some(requester) = track.requester {
+					page_content.push_str(&format!(" ({})", requester.mention()));
+				}
+				page_content.push('\n');
+			}
+
+			if !history.tracks.is_empty() {
+				page_content.push_str(&format!(
+					"\n\nPage {} of {} ({} songs total)",
+					page,
+					history.tracks.len().div_ceil(PAGE_SIZE),
+					history.tracks.len()
+				));
+			}
+		}
+	}
+
+	if page_content.is_empty() {
+		page_content = "Nothing was played yet".to_string();
+	}
+
+	invocation.send_embed(ctx, |embed| {
+		embed.title("Recently played").description(page_content)
+	}).await?;
+
+	Ok(())
+}
+
+pub async fn handle_previous(ctx: &Context, invocation: &Invocation<'_>) -> CommandResult {
+	let backend = music_backend(ctx, invocation).await?;
+	let guild_id = invocation.guild_id().unwrap();
+
+	let data = match backend.data(guild_id).await {
+		Some(data) => data,
+		None => {
+			invocation.reply(ctx, "Not in a channel").await?;
+
+			return Ok(());
+		}
+	};
+
+	let current = backend.now_playing(guild_id).await;
+
+	let previous = {
+		let mut data = data.write().await;
+		let history = data.entry::<History>().or_insert_with(History::default);
+		let previous = history.tracks.pop_front();
+
+		if previous.is_some() {
+			history.ignore = current.as_ref().map(|current| current.track.id.clone());
+		}
+
+		previous
+	};
+
+	let previous = match previous {
+		Some(previous) => previous,
+		None => {
+			invocation.say(ctx, "Nothing was played yet").await?;
+
+			return Ok(());
+		}
+	};
+
+	if backend.replay(guild_id, &previous).await? {
+		invocation.say(ctx, format!("Playing `{}` again", previous.title)).await?;
+	} else {
+		invocation.say(ctx, format!("Could not play `{}` again", previous.title)).await?;
+
+		// Nothing changed, the history stays as it was
+		if let Some(history) = data.write().await.get_mut::<History>() {
+			history.ignore = None;
+			history.tracks.push_front(previous);
+		}
+	}
+
+	Ok(())
+}
@@ -1,44 +1,56 @@
 use serenity::framework::standard::CommandResult;
 use serenity::framework::standard::macros::command;
 use serenity::model::prelude::Message;
-use serenity::prelude::Context;
+use serenity::prelude::{Context, Mentionable};
 
-use crate::commands::music::handlers::Lavalink;
-use crate::commands::music::util::format_millis;
+use crate::commands::music::filter::Filters;
+use crate::commands::music::util::{format_millis, music_backend, LoopMode};
+use crate::invocation::Invocation;
 
 #[command]
 #[description("Gives info about the currently running song.")]
 async fn info(ctx: &Context, msg: &Message) -> CommandResult {
-	let data = ctx.data.read().await;
-	let lava_client = data.get::<Lavalink>().unwrap().clone();
+	handle_info(ctx, &msg.into()).await
+}
 
-	if let Some(node) = lava_client.nodes().await.get(&msg.guild_id.unwrap().0) {
-		if let Some(track) = &node.now_playing {
-			let duration = track.track.info.as_ref().unwrap().length;
-			let duration_left = duration - track.track.info.as_ref().unwrap().position;
-			msg.channel_id.send_message(
-				&ctx.http,
-				|message| {
-					message.embed(
-						|embed| {
-							embed.field("Title: ", &track.track.info.as_ref().unwrap().title, false)
-								.field("Link: ", &track.track.info.as_ref().unwrap().uri, false)
-								.field("Duration: ", format_millis(duration), false)
-								.field("Duration left: ", format_millis(duration_left), false)
-						}
-					)
-				},
+pub async fn handle_info(ctx: &Context, invocation: &Invocation<'_>) -> CommandResult {
+	let backend = music_backend(ctx, invocation).await?;
+	let guild_id = invocation.guild_id().unwrap();
+
+	let (loop_mode, filters) = match backend.data(guild_id).await {
+		Some(data) => {
+			let data = data.read().await;
+
+			(
+				data.get::<LoopMode>().copied().unwrap_or(LoopMode::Off),
+				data.get::<Filters>().cloned().unwrap_or_default(),
 			)
-				.await?;
-		} else {
-			msg.channel_id
-				.say(&ctx.http, "Nothing is playing at the moment.")
-				.await?;
 		}
+		None => (LoopMode::Off, Filters::default()),
+	};
+
+	if let Some(now_playing) = backend.now_playing(guild_id).await {
+		let track = now_playing.track;
+		let duration_left = track.length.saturating_sub(now_playing.position);
+		invocation.send_embed(ctx, |embed| {
+			embed.field("Title: ", &track.title, false);
+			if let Some(uri) = &track.uri {
+				embed.field("Link: ", uri, false);
+			}
+			if let Some(requester) = track.requester {
+				embed.field("Requested by: ", requester.mention(), false);
+			}
+			embed
+				.field("Duration: ", format_millis(track.length), false)
+				.field("Duration left: ", format_millis(duration_left), false)
+				.field("Loop: ", loop_mode, false)
+				.field("Filters: ", filters, false)
+		})
+			.await?;
 	} else {
-		msg.channel_id
-			.say(&ctx.http, "Nothing is playing at the moment.")
+		invocation
+			.say(ctx, "Nothing is playing at the moment.")
 			.await?;
 	}
 	Ok(())
-}
\ No newline at end of file
+}
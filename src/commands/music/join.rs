@@ -5,57 +5,51 @@ use serenity::{
 			macros::command,
 		},
 	},
-	model::{channel::Message},
+	model::channel::Message,
 };
 use serenity::prelude::{Context, Mentionable};
 
-use crate::commands::music::handlers::Lavalink;
-use crate::commands::music::util::set_caller_channel;
+use crate::commands::music::backend::{Backend, ConnectError};
+use crate::invocation::Invocation;
 
 #[command]
 #[description("Lets the bot join into your voice channel.")]
 async fn join(ctx: &Context, msg: &Message) -> CommandResult {
-	let guild = msg.guild(&ctx.cache).await.unwrap();
-	let guild_id = guild.id;
+	handle_join(ctx, &msg.into()).await
+}
+
+pub async fn handle_join(ctx: &Context, invocation: &Invocation<'_>) -> CommandResult {
+	// The voice states are only known while the guild is cached
+	let guild = match invocation.guild_id().unwrap().to_guild_cached(&ctx.cache).await {
+		Some(guild) => guild,
+		None => {
+			invocation.reply(ctx, "Could not find this server, try again in a moment").await?;
+
+			return Ok(());
+		}
+	};
 
 	let channel_id = guild
 		.voice_states
-		.get(&msg.author.id)
+		.get(&invocation.author().id)
 		.and_then(|voice_state| voice_state.channel_id);
 
 	let connect_to = match channel_id {
 		Some(channel) => channel,
 		None => {
-			msg.reply(ctx, "Please join a Voice channel before asking me to join.").await?;
+			invocation.reply(ctx, "Please join a Voice channel before asking me to join.").await?;
 
 			return Ok(());
 		}
 	};
 
-	let manager = songbird::get(ctx).await.unwrap().clone();
+	let backend = ctx.data.read().await.get::<Backend>().unwrap().clone();
 
-	let (_, handler) = manager.join_gateway(guild_id, connect_to).await;
-
-	match handler {
-		Ok(connection_info) => {
-			let data = ctx.data.read().await;
-			let lava_client = data.get::<Lavalink>().unwrap().clone();
-			lava_client.create_session(&connection_info).await?;
-
-			if let Some(node) = lava_client.nodes().await.get(guild_id.as_u64()) {
-				set_caller_channel(&node, ctx.http.clone(), msg.channel_id).await;
-			}
-
-			msg.channel_id
-				.say(&ctx.http, &format!("Joined {}", connect_to.mention()))
-				.await?;
-		}
-		Err(why) => {
-			msg.channel_id
-				.say(&ctx.http, format!("Error joining the channel: {}", why))
-				.await?;
-		}
-	}
+	match backend.connect(ctx, guild.id, connect_to, invocation.channel_id()).await {
+		Ok(()) => invocation.say(ctx, &format!("Joined {}", connect_to.mention())).await?,
+		Err(ConnectError::Other(why)) => return Err(why),
+		Err(why) => invocation.say(ctx, why.to_string()).await?,
+	};
 
 	Ok(())
 }
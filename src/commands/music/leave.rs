@@ -1,44 +1,44 @@
 use serenity::framework::standard::CommandResult;
 use serenity::framework::standard::macros::command;
-use serenity::model::prelude::Message;
+use serenity::model::prelude::{GuildId, Message};
 use serenity::prelude::Context;
+use songbird::error::JoinError;
 
-use crate::commands::music::handlers::Lavalink;
+use crate::commands::music::backend::Backend;
+use crate::commands::music::dj::DJ_CHECK;
+use crate::invocation::Invocation;
 
 #[command]
+#[checks(DJ)]
 #[aliases("fuckoff")]
 #[description("Lets the bot leave from the voice channel.\nCleans up; The queue will get emptied.")]
 async fn leave(ctx: &Context, msg: &Message) -> CommandResult {
-	let guild = msg.guild(&ctx.cache).await.unwrap();
-	let guild_id = guild.id;
+	handle_leave(ctx, &msg.into()).await
+}
+
+pub async fn handle_leave(ctx: &Context, invocation: &Invocation<'_>) -> CommandResult {
+	let guild_id = invocation.guild_id().unwrap();
 
 	let manager = songbird::get(ctx).await.unwrap().clone();
 	let has_handler = manager.get(guild_id).is_some();
 
 	if has_handler {
-		if let Err(e) = manager.remove(guild_id).await {
-			msg.channel_id.say(&ctx.http, format!("Failed: {:?}", e)).await?;
-		}
-
-		//Clean up the player, event loops and data on leave
-		//See https://docs.rs/lavalink-rs/0.7.2/lavalink_rs/struct.LavalinkClient.html#method.destroy
-		{
-			let data = ctx.data.read().await;
-			let lava_client = data.get::<Lavalink>().unwrap().clone();
-			lava_client.destroy(guild_id).await?;
-			{
-				let nodes = lava_client.nodes().await;
-				nodes.remove(&guild_id.0);
-
-				let loops = lava_client.loops().await;
-				loops.remove(&guild_id.0);
-			}
+		if let Err(e) = disconnect(ctx, guild_id).await {
+			invocation.say(ctx, format!("Failed: {:?}", e)).await?;
 		}
 
-		msg.channel_id.say(&ctx.http, "Left voice channel").await?;
+		invocation.say(ctx, "Left voice channel").await?;
 	} else {
-		msg.reply(ctx, "Not in a voice channel.\nYou can let me join your voice channel with the join command").await?;
+		invocation.reply(ctx, "Not in a voice channel.\nYou can let me join your voice channel with the join command").await?;
 	}
 
 	Ok(())
-}
\ No newline at end of file
+}
+
+/// Leaves the voice channel of the guild and cleans up its player.
+/// The cleanup happens even if leaving fails, the error is returned afterwards.
+pub async fn disconnect(ctx: &Context, guild_id: GuildId) -> Result<(), JoinError> {
+	let backend = ctx.data.read().await.get::<Backend>().unwrap().clone();
+
+	backend.disconnect(ctx, guild_id).await
+}
@@ -0,0 +1,248 @@
+use std::collections::HashSet;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serenity::framework::standard::{Args, CommandResult};
+use serenity::framework::standard::macros::command;
+use serenity::model::prelude::Message;
+use serenity::prelude::Context;
+
+use crate::commands::music::util::music_backend;
+use crate::config::ConfigData;
+use crate::invocation::Invocation;
+
+/// Files with other extensions are left out of the list.
+const EXTENSIONS: &[&str] = &["mp3", "ogg", "opus", "flac", "wav", "m4a", "aac", "webm", "mka"];
+
+/// More files don't fit into an embed.
+const MAX_LISTED: usize = 50;
+
+const USAGE: &str = "Use `list`, `list <name>` or `play <name>`";
+
+pub enum LocalAction {
+	/// Lists all files, or the ones matching the name.
+	List(Option<String>),
+	Play(String),
+}
+
+/// An audio file somewhere inside the media directory.
+struct MediaFile {
+	/// The path relative to the media directory, as it is shown to users.
+	name: String,
+	path: PathBuf,
+}
+
+#[command]
+#[description("Plays audio files from the media directory of the bot.\n`list` shows the files, or only the ones matching a name. `play` adds the file whose name matches best to the queue, the name doesn't have to be exact.")]
+#[usage("$action $name")]
+#[example("list")]
+#[example("list intro")]
+#[example("play airhorn")]
+async fn local(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+	let action = args.single::<String>().unwrap_or_default().to_lowercase();
+	let name = args.rest().trim().to_string();
+
+	let action = match action.as_str() {
+		"list" if name.is_empty() => Some(LocalAction::List(None)),
+		"list" => Some(LocalAction::List(Some(name))),
+		"play" if !name.is_empty() => Some(LocalAction::Play(name)),
+		_ => None,
+	};
+
+	match action {
+		Some(action) => handle_local(ctx, &msg.into(), action).await,
+		None => {
+			msg.reply(&ctx.http, USAGE).await?;
+
+			Ok(())
+		}
+	}
+}
+
+pub async fn handle_local(ctx: &Context, invocation: &Invocation<'_>, action: LocalAction) -> CommandResult {
+	let directory = ctx.data.read().await
+		.get::<ConfigData>().unwrap()
+		.music.as_ref()
+		.and_then(|music| music.media_directory.clone());
+
+	let directory = match directory {
+		Some(directory) => directory,
+		None => {
+			invocation.reply(ctx, "There is no media directory set up for this bot").await?;
+
+			return Ok(());
+		}
+	};
+
+	let files = match media_files(directory.clone()).await {
+		Ok(files) => files,
+		Err(why) => {
+			log::error!("Could not read the media directory {}: {:?}", directory, why);
+			invocation.say(ctx, "The media directory can't be read right now").await?;
+
+			return Ok(());
+		}
+	};
+
+	match action {
+		LocalAction::List(filter) => {
+			let names = match &filter {
+				Some(filter) => ranked(&files, filter).into_iter().map(|file| file.name.as_str()).collect::<Vec<_>>(),
+				None => files.iter().map(|file| file.name.as_str()).collect(),
+			};
+
+			if names.is_empty() {
+				invocation.say(ctx, "No files found").await?;
+
+				return Ok(());
+			}
+
+			let mut content = names
+				.iter()
+				.take(MAX_LISTED)
+				.map(|name| format!("`{}`", name))
+				.collect::<Vec<_>>()
+				.join("\n");
+
+			if names.len() > MAX_LISTED {
+				content.push_str(&format!("\n\n...and {} more, add a name to narrow it down", names.len() - MAX_LISTED));
+			}
+
+			invocation.send_embed(ctx, |embed| {
+				embed.title("Media files").description(content)
+			}).await?;
+		}
+		LocalAction::Play(name) => {
+			let guild_id = invocation.guild_id().unwrap();
+
+			let manager = songbird::get(ctx).await.unwrap().clone();
+			if manager.get(guild_id).is_none() {
+				invocation.say(ctx, "Use `join` first, to connect the bot to your current voice channel.").await?;
+
+				return Ok(());
+			}
+
+			let file = match ranked(&files, &name).into_iter().next() {
+				Some(file) => file,
+				None => {
+					invocation.reply(ctx, format!("No file matches `{}`", name)).await?;
+
+					return Ok(());
+				}
+			};
+
+			let backend = music_backend(ctx, invocation).await?;
+
+			if backend.enqueue_file(guild_id, &file.path, invocation.author().id).await? == 0 {
+				invocation.say(ctx, format!("Could not play `{}`", file.name)).await?;
+			} else {
+				invocation.say(ctx, format!("Added `{}`", file.name)).await?;
+			}
+		}
+	}
+
+	Ok(())
+}
+
+/// All audio files in the directory and its subdirectories, sorted by name.
+///
+/// Only files that really are inside the directory are returned, symlinks pointing elsewhere are left out.
+/// Names given by users are only ever matched against this list and never joined onto a path,
+/// so nothing outside of the directory can be played.
+async fn media_files(directory: String) -> io::Result<Vec<MediaFile>> {
+	tokio::task::spawn_blocking(move || {
+		let root = Path::new(&directory).canonicalize()?;
+
+		let mut files = Vec::new();
+		let mut visited = HashSet::new();
+		let mut directories = vec![root.clone()];
+
+		while let Some(directory) = directories.pop() {
+			// Symlinks to a parent directory would be walked forever otherwise
+			if !visited.insert(directory.clone()) {
+				continue;
+			}
+
+			for entry in std::fs::read_dir(&directory)? {
+				// Broken symlinks can't be canonicalized and can't be played either
+				let path = match entry.and_then(|entry| entry.path().canonicalize()) {
+					Ok(path) => path,
+					Err(_) => continue,
+				};
+
+				if !path.starts_with(&root) {
+					continue;
+				}
+
+				if path.is_dir() {
+					directories.push(path);
+				} else if has_audio_extension(&path) {
+					let name = path.strip_prefix(&root).unwrap().to_string_lossy().replace('\\', "/");
+					files.push(MediaFile { name, path });
+				}
+			}
+		}
+
+		files.sort_by(|a, b| a.name.cmp(&b.name));
+		files.dedup_by(|a, b| a.path == b.path);
+
+		Ok(files)
+	}).await.map_err(io::Error::other)?
+}
+
+/// Whether the file looks like audio that can be played, judging by its extension.
+pub fn has_audio_extension(path: &Path) -> bool {
+	path.extension()
+		.and_then(|extension| extension.to_str())
+		.is_some_and(|extension| EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+}
+
+/// The files matching the query, best match first.
+fn ranked<'a>(files: &'a [MediaFile], query: &str) -> Vec<&'a MediaFile> {
+	let mut matches = files
+		.iter()
+		.filter_map(|file| Some((match_score(&file.name, query)?, file)))
+		.collect::<Vec<_>>();
+
+	matches.sort_by_key(|(score, _)| *score);
+
+	matches.into_iter().map(|(_, file)| file).collect()
+}
+
+/// How well the name of a file matches the query, lower is better. `None` if it doesn't match at all.
+///
+/// Exact names come first, then names starting with the query, containing it, containing all of its words
+/// and finally names that contain its letters in the same order. Shorter names win within each of those.
+fn match_score(name: &str, query: &str) -> Option<(u8, usize)> {
+	let name = normalize(name);
+	let query = normalize(query);
+	let stem = name.rsplit('/').next().unwrap_or(&name);
+	let stem = stem.rsplit_once('.').map_or(stem, |(stem, _)| stem);
+
+	let tier = if stem == query || name == query {
+		0
+	} else if stem.starts_with(&query) {
+		1
+	} else if name.contains(&query) {
+		2
+	} else if query.split_whitespace().all(|word| name.contains(word)) {
+		3
+	} else if is_subsequence(&query.replace(' ', ""), &name) {
+		4
+	} else {
+		return None;
+	};
+
+	Some((tier, name.len()))
+}
+
+/// Case and the different ways to separate words don't matter when matching names.
+fn normalize(name: &str) -> String {
+	name.to_lowercase().replace(['_', '-'], " ")
+}
+
+fn is_subsequence(needle: &str, haystack: &str) -> bool {
+	let mut haystack = haystack.chars();
+
+	needle.chars().all(|c| haystack.any(|h| h == c))
+}
@@ -1,15 +1,31 @@
 mod util;
 
+pub mod backend;
 pub mod queue;
 pub mod join;
 pub mod leave;
+pub mod local;
 pub mod play;
+pub mod playlist;
 pub mod skip;
+pub mod soundboard;
 pub mod info;
 pub mod remove;
+pub mod reorder;
 pub mod clear;
 pub mod pause;
+pub mod persistence;
 pub mod resume;
 pub mod shuffle;
+pub mod repeat;
+pub mod seek;
+pub mod volume;
+pub mod filter;
+pub mod dj;
+pub mod export;
+pub mod auto_leave;
 pub mod handlers;
+pub mod history;
+pub mod nodes;
+pub mod panel;
 pub mod youtube_search;
\ No newline at end of file
@@ -0,0 +1,393 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_tungstenite::tungstenite::Message as WebsocketMessage;
+use futures::SinkExt;
+use lavalink_rs::LavalinkClient;
+use lavalink_rs::model::{LavalinkResult, Stats, TrackQueue};
+use serenity::model::id::{GuildId, UserId};
+use serenity::prelude::{Mutex, RwLock, TypeMapKey};
+use songbird::Songbird;
+
+use crate::commands::music::filter::{apply_filters, Filters};
+use crate::commands::music::handlers::LavalinkHandler;
+use crate::commands::music::util::announce;
+use crate::commands::music::volume::DEFAULT_VOLUME;
+use crate::config::LavalinkNode;
+
+/// How often lost nodes are noticed, reconnected and their players moved.
+const CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// All lavalink nodes and which guild plays on which of them.
+///
+/// New sessions go to the least loaded node. When a node loses its connection,
+/// the players of its guilds are moved to another node, queue and position included.
+pub struct LavalinkNodes {
+	bot_id: UserId,
+	nodes: Vec<NodeState>,
+	sessions: Mutex<HashMap<GuildId, Session>>,
+}
+
+struct NodeState {
+	config: LavalinkNode,
+	/// `None` while the node is not connected.
+	client: RwLock<Option<LavalinkClient>>,
+	/// Updated by the [`LavalinkHandler`] of the client.
+	stats: Arc<RwLock<Option<Stats>>>,
+}
+
+/// The player state of a guild lives in the client it was created with. That client is kept,
+/// even after it lost its connection, so the state can still be moved to another node.
+struct Session {
+	node: usize,
+	client: LavalinkClient,
+	region: Option<String>,
+}
+
+impl LavalinkNodes {
+	/// Nodes that can't be reached now are retried by [`LavalinkNodes::watch`].
+	pub async fn connect(bot_id: UserId, configs: Vec<LavalinkNode>) -> LavalinkNodes {
+		let nodes = configs
+			.into_iter()
+			.map(|config| NodeState {
+				config,
+				client: RwLock::new(None),
+				stats: Arc::new(RwLock::new(None)),
+			})
+			.collect();
+
+		let lavalink_nodes = LavalinkNodes {
+			bot_id,
+			nodes,
+			sessions: Mutex::new(HashMap::new()),
+		};
+
+		for node in &lavalink_nodes.nodes {
+			log::info!(
+				"Connecting to lavalink node '{}' at {}://{}:{}",
+				node.config.name,
+				if node.config.ssl { "wss" } else { "ws" },
+				node.config.host,
+				node.config.port,
+			);
+
+			match lavalink_nodes.build_client(node).await {
+				Ok(client) => *node.client.write().await = Some(client),
+				Err(why) => log::error!("Could not connect to lavalink node '{}': {:?}", node.config.name, why),
+			}
+		}
+
+		lavalink_nodes
+	}
+
+	/// The client of the node the guild plays on.
+	/// Guilds without a session get the least loaded node, e.g. for searching tracks.
+	///
+	/// Returns `None` if no node is connected.
+	pub async fn client(&self, guild_id: GuildId) -> Option<LavalinkClient> {
+		let sessions = self.sessions.lock().await;
+
+		if let Some(session) = sessions.get(&guild_id) {
+			return Some(session.client.clone());
+		}
+
+		let index = self.least_loaded(None, &sessions).await?;
+
+		self.nodes[index].client.read().await.clone()
+	}
+
+	/// Picks the node for a new session of the guild, preferring nodes in the given voice region.
+	///
+	/// Returns `None` if no node is connected.
+	pub async fn assign(&self, guild_id: GuildId, region: Option<&str>) -> Option<LavalinkClient> {
+		let mut sessions = self.sessions.lock().await;
+
+		if let Some(session) = sessions.get(&guild_id) {
+			if self.is_connected(session).await {
+				return Some(session.client.clone());
+			}
+		}
+
+		let index = self.least_loaded(region, &sessions).await?;
+		let client = self.nodes[index].client.read().await.clone()?;
+
+		log::debug!("Assigned guild {} to lavalink node '{}'", guild_id, self.nodes[index].config.name);
+		sessions.insert(guild_id, Session {
+			node: index,
+			client: client.clone(),
+			region: region.map(str::to_string),
+		});
+
+		Some(client)
+	}
+
+	/// The guilds that have a session, with the client their player lives in.
+	pub async fn sessions(&self) -> Vec<(GuildId, LavalinkClient)> {
+		self.sessions
+			.lock().await
+			.iter()
+			.map(|(guild_id, session)| (*guild_id, session.client.clone()))
+			.collect()
+	}
+
+	/// Forgets the node of the guild, the next session can go to another one.
+	pub async fn release(&self, guild_id: GuildId) {
+		self.sessions.lock().await.remove(&guild_id);
+	}
+
+	/// Reconnects lost nodes and moves the players of their guilds, never returns.
+	pub async fn watch(self: Arc<Self>, songbird: Arc<Songbird>) {
+		loop {
+			tokio::time::sleep(CHECK_INTERVAL).await;
+
+			self.check_connections().await;
+			self.move_lost_sessions(&songbird).await;
+		}
+	}
+
+	async fn build_client(&self, node: &NodeState) -> LavalinkResult<LavalinkClient> {
+		LavalinkClient::builder(self.bot_id)
+			.set_host(&node.config.host)
+			.set_port(node.config.port)
+			.set_is_ssl(node.config.ssl)
+			.set_password(&node.config.password)
+			.build(LavalinkHandler { stats: Arc::clone(&node.stats) })
+			.await
+	}
+
+	async fn check_connections(&self) {
+		for node in &self.nodes {
+			let client = node.client.read().await.clone();
+
+			match client {
+				Some(client) if is_alive(&client).await => {}
+				Some(_) => {
+					log::warn!("Lost connection to lavalink node '{}'", node.config.name);
+					*node.client.write().await = None;
+					*node.stats.write().await = None;
+				}
+				None => match self.build_client(node).await {
+					Ok(client) => {
+						log::info!("Reconnected to lavalink node '{}'", node.config.name);
+						*node.client.write().await = Some(client);
+					}
+					Err(why) => log::debug!("Lavalink node '{}' is still unreachable: {:?}", node.config.name, why),
+				},
+			}
+		}
+	}
+
+	async fn move_lost_sessions(&self, songbird: &Songbird) {
+		let mut sessions = self.sessions.lock().await;
+		let mut lost = Vec::new();
+
+		for (guild_id, session) in sessions.iter() {
+			if !self.is_connected(session).await {
+				lost.push(*guild_id);
+			}
+		}
+
+		for guild_id in lost {
+			let region = sessions[&guild_id].region.clone();
+
+			// With no node left the sessions stay where they are until one comes back
+			let index = match self.least_loaded(region.as_deref(), &sessions).await {
+				Some(index) => index,
+				None => return,
+			};
+
+			let client = match self.nodes[index].client.read().await.clone() {
+				Some(client) => client,
+				None => return,
+			};
+
+			let lost_session = sessions.remove(&guild_id).unwrap();
+			let name = &self.nodes[index].config.name;
+
+			match move_player(guild_id, &lost_session.client, &client, songbird).await {
+				Ok(true) => {
+					log::info!("Moved the player of guild {} to lavalink node '{}'", guild_id, name);
+					announce_move(&client, guild_id).await;
+
+					sessions.insert(guild_id, Session {
+						node: index,
+						client,
+						region,
+					});
+				}
+				Ok(false) => log::debug!("Dropped the session of guild {}, it is not in a voice channel anymore", guild_id),
+				Err(why) => log::error!("Could not move the player of guild {} to lavalink node '{}': {:?}", guild_id, name, why),
+			}
+		}
+	}
+
+	/// Whether the session's client is still the connected client of its node.
+	async fn is_connected(&self, session: &Session) -> bool {
+		match &*self.nodes[session.node].client.read().await {
+			Some(client) => Arc::ptr_eq(&client.inner, &session.client.inner),
+			None => false,
+		}
+	}
+
+	/// Only nodes in the region are considered if any of them is connected.
+	async fn least_loaded(&self, region: Option<&str>, sessions: &HashMap<GuildId, Session>) -> Option<usize> {
+		let mut candidates = Vec::new();
+
+		for (index, node) in self.nodes.iter().enumerate() {
+			if node.client.read().await.is_none() {
+				continue;
+			}
+
+			let node_sessions = sessions.values().filter(|session| session.node == index).count();
+			let penalty = load_penalty(node.stats.read().await.as_ref(), node_sessions);
+			let in_region = region.is_some() && node.config.region.as_deref() == region;
+
+			candidates.push((index, in_region, penalty));
+		}
+
+		let any_in_region = candidates.iter().any(|(_, in_region, _)| *in_region);
+
+		candidates
+			.into_iter()
+			.filter(|(_, in_region, _)| *in_region || !any_in_region)
+			.min_by(|(_, _, a), (_, _, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+			.map(|(index, _, _)| index)
+	}
+}
+
+/// How busy a node is, lower is better. Weighted the same way as other lavalink clients do it.
+///
+/// Stats only arrive about once a minute, so the sessions assigned since then count as
+/// playing players too, otherwise a burst of joins would all land on the same node.
+fn load_penalty(stats: Option<&Stats>, sessions: usize) -> f64 {
+	let stats = match stats {
+		Some(stats) => stats,
+		None => return sessions as f64,
+	};
+
+	let players = (stats.playing_players.max(0) as usize).max(sessions) as f64;
+	let cpu = 1.05f64.powf(100.0 * stats.cpu.system_load) * 10.0 - 10.0;
+	let frames = match &stats.frame_stats {
+		Some(frames) => {
+			let deficit = 1.03f64.powf(500.0 * (frames.deficit as f64 / 3000.0)) * 600.0 - 600.0;
+			let nulled = (1.03f64.powf(500.0 * (frames.nulled as f64 / 3000.0)) * 300.0 - 300.0) * 2.0;
+
+			deficit + nulled
+		}
+		None => 0.0,
+	};
+
+	players + cpu + frames
+}
+
+/// lavalink-rs doesn't notice when its websocket closes, but sending a ping over a closed one fails.
+async fn is_alive(client: &LavalinkClient) -> bool {
+	let mut inner = client.inner.lock().await;
+
+	inner.socket_write.send(WebsocketMessage::Ping(Vec::new())).await.is_ok()
+}
+
+/// Continues the player of `from` on `to` with the same queue, position and node data.
+///
+/// Returns `false` if there was nothing to move because the bot left the voice channel.
+async fn move_player(guild_id: GuildId, from: &LavalinkClient, to: &LavalinkClient, songbird: &Songbird) -> LavalinkResult<bool> {
+	// Removing the node also stops the queue loop of the old client
+	let old_node = from.nodes().await.remove(&guild_id.0).map(|(_, node)| node);
+	from.loops().await.remove(&guild_id.0);
+
+	let connection_info = match songbird.get(guild_id) {
+		Some(call) => call.lock().await.current_connection().cloned(),
+		None => None,
+	};
+
+	let (old_node, connection_info) = match (old_node, connection_info) {
+		(Some(old_node), Some(connection_info)) => (old_node, connection_info),
+		_ => return Ok(false),
+	};
+
+	to.create_session(&connection_info).await?;
+
+	if let Some(mut node) = to.nodes().await.get_mut(&guild_id.0) {
+		node.data = Arc::clone(&old_node.data);
+		node.volume = old_node.volume;
+	}
+
+	if old_node.volume != DEFAULT_VOLUME {
+		to.volume(guild_id, old_node.volume).await?;
+	}
+
+	let filters = old_node.data.read().await.get::<Filters>().cloned();
+	if let Some(filters) = filters {
+		apply_filters(to, guild_id, &filters).await?;
+	}
+
+	let position = old_node.now_playing
+		.as_ref()
+		.and_then(|now_playing| now_playing.track.info.as_ref())
+		.map(|info| info.position);
+
+	resume_queue(to, guild_id, old_node.queue, position, old_node.is_paused).await?;
+
+	Ok(true)
+}
+
+/// Plays the queue on a player that has just been created, continuing the first track at `position`.
+pub async fn resume_queue(
+	to: &LavalinkClient,
+	guild_id: GuildId,
+	mut queue: Vec<TrackQueue>,
+	position: Option<u64>,
+	paused: bool,
+) -> LavalinkResult<()> {
+	if queue.is_empty() {
+		return Ok(());
+	}
+
+	// While a track plays it is the first one in the queue
+	let mut first = queue.remove(0);
+	if let Some(position) = position {
+		first.start_time = position;
+	}
+
+	// lavalink-rs can't start a track paused, the pause is sent once it started (see `track_start`)
+	let data = to.nodes().await.get(&guild_id.0).map(|node| Arc::clone(&node.data));
+	if let (true, Some(data)) = (paused, data) {
+		data.write().await.insert::<ResumePaused>(());
+	}
+
+	let mut play = to
+		.play(guild_id, first.track)
+		.start_time(Duration::from_millis(first.start_time));
+
+	if let Some(end_time) = first.end_time {
+		play = play.finish_time(Duration::from_millis(end_time));
+	}
+
+	if let Some(requester) = first.requester {
+		play = play.requester(requester);
+	}
+
+	play.queue().await?;
+
+	if let Some(mut node) = to.nodes().await.get_mut(&guild_id.0) {
+		node.queue.extend(queue);
+		node.is_paused = paused;
+	}
+
+	Ok(())
+}
+
+/// Set in the player data when the next track that starts should be paused right away.
+///
+/// A new player only stays paused if the pause arrives after its first track started.
+pub struct ResumePaused;
+
+impl TypeMapKey for ResumePaused {
+	type Value = ();
+}
+
+async fn announce_move(client: &LavalinkClient, guild_id: GuildId) {
+	announce(client, guild_id, "Lost the connection to the music server, continuing on another one").await;
+}
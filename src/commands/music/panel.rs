@@ -0,0 +1,377 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use serenity::builder::{CreateComponents, CreateEmbed};
+use serenity::framework::standard::CommandResult;
+use serenity::model::id::{ChannelId, GuildId, MessageId};
+use serenity::model::interactions::{InteractionApplicationCommandCallbackDataFlags, InteractionResponseType};
+use serenity::model::interactions::message_component::{ButtonStyle, MessageComponentInteraction};
+use serenity::prelude::{Context, Mentionable, RwLock, TypeMap, TypeMapKey};
+
+use crate::commands::music::dj::{check_dj, DJ_COMMANDS};
+use crate::commands::music::backend::{Backend, MusicBackend, NowPlaying};
+use crate::commands::music::leave::handle_leave;
+use crate::commands::music::pause::handle_pause;
+use crate::commands::music::repeat::handle_loop;
+use crate::commands::music::resume::handle_unpause;
+use crate::commands::music::skip::handle_skip;
+use crate::commands::music::util::{CallerChannel, format_millis, loop_mode, LoopMode};
+use crate::invocation::Invocation;
+
+/// How often the progress bar of a playing song is moved forward.
+/// Lavalink only reports the position every few seconds anyway.
+const UPDATE_INTERVAL: Duration = Duration::from_secs(10);
+const PROGRESS_BAR_LENGTH: usize = 20;
+
+/// The custom ids of the panel's buttons start with this, other buttons are left alone.
+const BUTTON_PREFIX: &str = "player_";
+
+/// The now playing message of a guild. Edited for every song instead of posting a new one.
+#[derive(Clone)]
+pub struct PlayerPanel {
+	channel_id: ChannelId,
+	message_id: MessageId,
+	/// What the message shows right now, so it is only edited when something changed.
+	shown: Shown,
+}
+
+#[derive(Clone, PartialEq)]
+struct Shown {
+	/// The id of the song.
+	track: Option<String>,
+	paused: bool,
+	queued: usize,
+	loop_mode: LoopMode,
+}
+
+impl TypeMapKey for PlayerPanel {
+	type Value = PlayerPanel;
+}
+
+/// Keeps the progress bars of all panels moving, see [`watch`].
+#[derive(Default)]
+pub struct PanelUpdater {
+	watching: AtomicBool,
+}
+
+impl TypeMapKey for PanelUpdater {
+	type Value = Arc<PanelUpdater>;
+}
+
+/// What the panel shows.
+struct PlayerState {
+	now_playing: Option<NowPlaying>,
+	/// Songs after the current one.
+	queued: usize,
+	loop_mode: LoopMode,
+	data: Arc<RwLock<TypeMap>>,
+}
+
+async fn player_state(backend: &dyn MusicBackend, guild_id: GuildId) -> Option<PlayerState> {
+	let data = backend.data(guild_id).await?;
+	let now_playing = backend.now_playing(guild_id).await;
+	let queued = backend.queue(guild_id).await.len();
+	let loop_mode = loop_mode(&data).await;
+
+	Some(PlayerState { now_playing, queued, loop_mode, data })
+}
+
+impl PlayerState {
+	fn paused(&self) -> bool {
+		self.now_playing.as_ref().is_some_and(|now_playing| now_playing.paused)
+	}
+
+	fn shown(&self) -> Shown {
+		Shown {
+			track: self.now_playing.as_ref().map(|now_playing| now_playing.track.id.clone()),
+			paused: self.paused(),
+			queued: self.queued,
+			loop_mode: self.loop_mode,
+		}
+	}
+}
+
+/// Shows the current song in the guild's panel, posting a new one if there is none
+/// in the channel music was last requested from.
+pub async fn show(backend: &dyn MusicBackend, guild_id: GuildId) {
+	let state = match player_state(backend, guild_id).await {
+		Some(state) => state,
+		None => return,
+	};
+
+	let (channel_id, http, panel) = {
+		let data = state.data.read().await;
+
+		match data.get::<CallerChannel>() {
+			Some(caller_channel) => (caller_channel.channel_id, caller_channel.http.clone(), data.get::<PlayerPanel>().cloned()),
+			None => return,
+		}
+	};
+
+	if let Some(panel) = panel {
+		if panel.channel_id == channel_id {
+			let edited = channel_id.edit_message(&http, panel.message_id, |message| {
+				message.embed(|embed| render(embed, &state)).components(|components| buttons(components, &state))
+			}).await;
+
+			if edited.is_ok() {
+				state.data.write().await.insert::<PlayerPanel>(PlayerPanel { shown: state.shown(), ..panel });
+
+				return;
+			}
+		} else {
+			// Music is requested somewhere else now, the panel moves along
+			let _ = panel.channel_id.delete_message(&http, panel.message_id).await;
+		}
+	}
+
+	let sent = channel_id.send_message(&http, |message| {
+		message.embed(|embed| render(embed, &state)).components(|components| buttons(components, &state))
+	}).await;
+
+	match sent {
+		Ok(message) => {
+			state.data.write().await.insert::<PlayerPanel>(PlayerPanel {
+				channel_id,
+				message_id: message.id,
+				shown: state.shown(),
+			});
+		}
+		Err(why) => log::warn!("Could not post the player of guild {}: {:?}", guild_id, why),
+	}
+}
+
+/// Updates the guild's panel, if it has one and it is out of date.
+pub async fn refresh(backend: &dyn MusicBackend, guild_id: GuildId) {
+	let state = match player_state(backend, guild_id).await {
+		Some(state) => state,
+		None => return,
+	};
+
+	let (panel, http) = {
+		let data = state.data.read().await;
+
+		match (data.get::<PlayerPanel>().cloned(), data.get::<CallerChannel>()) {
+			(Some(panel), Some(caller_channel)) => (panel, caller_channel.http.clone()),
+			_ => return,
+		}
+	};
+
+	let shown = state.shown();
+	let moving = shown.track.is_some() && !shown.paused;
+
+	if panel.shown == shown && !moving {
+		return;
+	}
+
+	let edited = panel.channel_id.edit_message(&http, panel.message_id, |message| {
+		message.embed(|embed| render(embed, &state)).components(|components| buttons(components, &state))
+	}).await;
+
+	match edited {
+		Ok(_) => {
+			state.data.write().await.insert::<PlayerPanel>(PlayerPanel { shown, ..panel });
+		}
+		// Most likely deleted, the next song gets a new one
+		Err(why) => {
+			log::debug!("Could not update the player of guild {}: {:?}", guild_id, why);
+			state.data.write().await.remove::<PlayerPanel>();
+		}
+	}
+}
+
+/// Takes the buttons off the panel of the player data, for when the bot leaves.
+pub async fn close(data: &RwLock<TypeMap>) {
+	let (panel, http) = {
+		let mut data = data.write().await;
+
+		match (data.remove::<PlayerPanel>(), data.get::<CallerChannel>()) {
+			(Some(panel), Some(caller_channel)) => (panel, caller_channel.http.clone()),
+			_ => return,
+		}
+	};
+
+	let _ = panel.channel_id.edit_message(&http, panel.message_id, |message| {
+		message
+			.embed(|embed| embed.title("Not playing anymore"))
+			.components(|components| components)
+	}).await;
+}
+
+/// Moves the progress bars of playing songs forward and catches up with changes made through commands,
+/// like the queue running out. Runs until the bot stops, only the first call does anything.
+pub async fn watch(ctx: Context) {
+	let (panel_updater, backend) = {
+		let data = ctx.data.read().await;
+
+		(data.get::<PanelUpdater>().unwrap().clone(), data.get::<Backend>().unwrap().clone())
+	};
+
+	// `ready` is sent again after reconnecting
+	if panel_updater.watching.swap(true, Ordering::SeqCst) {
+		return;
+	}
+
+	loop {
+		tokio::time::sleep(UPDATE_INTERVAL).await;
+
+		for guild_id in backend.players().await {
+			refresh(&*backend, guild_id).await;
+		}
+	}
+}
+
+/// Runs the command behind a button of a panel. Other buttons are ignored.
+pub async fn handle_button(ctx: &Context, component: &MessageComponentInteraction) {
+	let action = match component.data.custom_id.strip_prefix(BUTTON_PREFIX) {
+		Some(action) => action,
+		None => return,
+	};
+
+	let guild_id = match component.guild_id {
+		Some(guild_id) => guild_id,
+		None => return,
+	};
+
+	log::info!("Got player button '{}' by user '{}'", action, component.user.name);
+
+	// The answers are only interesting for whoever pressed the button
+	let deferred = component.create_interaction_response(&ctx.http, |response| {
+		response
+			.kind(InteractionResponseType::DeferredChannelMessageWithSource)
+			.interaction_response_data(|data| data.flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL))
+	}).await;
+
+	if let Err(why) = deferred {
+		log::error!("Error deferring player button '{}': {:?}", action, why);
+
+		return;
+	}
+
+	let invocation = Invocation::from(component);
+
+	if let Err(why) = press(ctx, &invocation, guild_id, action).await {
+		log::error!("Player button returned an error: {:?}", why);
+
+		if !invocation.has_responded() {
+			let _ = invocation.say(ctx, "Something went wrong while running this command").await;
+		}
+	}
+
+	if !invocation.has_responded() {
+		let _ = component.delete_original_interaction_response(&ctx.http).await;
+	}
+
+	let backend = ctx.data.read().await.get::<Backend>().unwrap().clone();
+	refresh(&*backend, guild_id).await;
+}
+
+async fn press(ctx: &Context, invocation: &Invocation<'_>, guild_id: GuildId, action: &str) -> CommandResult {
+	// Buttons can't use `#[checks(DJ)]` either, the same commands as for slash commands need a DJ
+	let command = match action {
+		"stop" => "leave",
+		action => action,
+	};
+
+	if DJ_COMMANDS.contains(&command) {
+		if let Err(reason) = check_dj(ctx, guild_id, invocation.author().id, command, None).await {
+			invocation.reply(ctx, reason).await?;
+
+			return Ok(());
+		}
+	}
+
+	match action {
+		"pause" => handle_pause(ctx, invocation).await,
+		"resume" => handle_unpause(ctx, invocation).await,
+		"skip" => handle_skip(ctx, invocation).await,
+		"stop" => handle_leave(ctx, invocation).await,
+		"loop" => {
+			let backend = ctx.data.read().await.get::<Backend>().unwrap().clone();
+			let loop_mode = match backend.data(guild_id).await {
+				Some(data) => loop_mode(&data).await,
+				None => LoopMode::Off,
+			};
+
+			let next = match loop_mode {
+				LoopMode::Off => LoopMode::Track,
+				LoopMode::Track => LoopMode::Queue,
+				LoopMode::Queue => LoopMode::Off,
+			};
+
+			handle_loop(ctx, invocation, Some(next)).await
+		}
+		_ => Ok(()),
+	}
+}
+
+fn render<'a>(embed: &'a mut CreateEmbed, state: &PlayerState) -> &'a mut CreateEmbed {
+	let (track, position) = match &state.now_playing {
+		Some(now_playing) => (&now_playing.track, now_playing.position),
+		None => return embed.title("Nothing playing").description("Add songs with the play command"),
+	};
+
+	let status = if state.paused() { "⏸" } else { "▶" };
+
+	let progress = if track.is_stream {
+		format!("{} 🔴 Live", status)
+	} else {
+		format!(
+			"{} {} {} / {}",
+			status,
+			progress_bar(position, track.length),
+			format_millis(position),
+			format_millis(track.length),
+		)
+	};
+
+	let title = match &track.uri {
+		Some(uri) => format!("[{}]({})", track.title, uri),
+		None => track.title.clone(),
+	};
+
+	embed
+		.title("**Now playing**")
+		.description(format!("{}\n\n{}", title, progress))
+		.footer(|footer| footer.text(format!("{} songs queued | Loop: {}", state.queued, state.loop_mode)));
+
+	if let Some(requester) = track.requester {
+		embed.field("Requested by", requester.mention(), false);
+	}
+
+	embed
+}
+
+fn buttons<'a>(components: &'a mut CreateComponents, state: &PlayerState) -> &'a mut CreateComponents {
+	components.create_action_row(|row| {
+		row
+			.create_button(|button| {
+				if state.paused() {
+					button.style(ButtonStyle::Primary).label("Resume").custom_id("player_resume")
+				} else {
+					button.style(ButtonStyle::Primary).label("Pause").custom_id("player_pause")
+				}
+			})
+			.create_button(|button| button.style(ButtonStyle::Secondary).label("Skip").custom_id("player_skip"))
+			.create_button(|button| button.style(ButtonStyle::Danger).label("Stop").custom_id("player_stop"))
+			.create_button(|button| {
+				button
+					.style(ButtonStyle::Secondary)
+					.label(format!("Loop: {}", state.loop_mode))
+					.custom_id("player_loop")
+			})
+	})
+}
+
+/// Like `▬▬▬▬▬🔘▬▬▬▬▬▬▬▬`, the knob is at the played part of the song.
+fn progress_bar(position: u64, length: u64) -> String {
+	let knob = match length {
+		0 => 0,
+		length => (position.min(length) as f64 / length as f64 * (PROGRESS_BAR_LENGTH - 1) as f64).round() as usize,
+	};
+
+	(0..PROGRESS_BAR_LENGTH)
+		.map(|index| if index == knob { '🔘' } else { '▬' })
+		.collect()
+}
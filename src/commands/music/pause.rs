@@ -3,17 +3,21 @@ use serenity::framework::standard::macros::command;
 use serenity::model::prelude::Message;
 use serenity::prelude::Context;
 
-use crate::commands::music::handlers::Lavalink;
+use crate::commands::music::util::music_backend;
+use crate::invocation::Invocation;
 
 #[command]
 #[description("Pauses the player. Still pauses if no song is currently running.")]
 async fn pause(ctx: &Context, msg: &Message) -> CommandResult {
-	let data = ctx.data.read().await;
-	let lava_client = data.get::<Lavalink>().unwrap().clone();
-	let guild_id = u64::from(msg.guild_id.unwrap());
+	handle_pause(ctx, &msg.into()).await
+}
 
-	lava_client.pause(guild_id).await?;
-	msg.channel_id.say(&ctx.http, "Paused player").await?;
+pub async fn handle_pause(ctx: &Context, invocation: &Invocation<'_>) -> CommandResult {
+	let backend = music_backend(ctx, invocation).await?;
+
+	backend.set_paused(invocation.guild_id().unwrap(), true).await?;
+
+	invocation.say(ctx, "Paused player").await?;
 
 	Ok(())
 }
\ No newline at end of file
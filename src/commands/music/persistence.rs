@@ -0,0 +1,161 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use serenity::model::id::{ChannelId, GuildId};
+use serenity::prelude::{Context, RwLock, TypeMap};
+use songbird::SongbirdKey;
+
+use crate::commands::music::backend::{Backend, MusicBackend, QueuedTrack};
+use crate::commands::music::filter::Filters;
+use crate::commands::music::util::CallerChannel;
+use crate::database::Database;
+
+/// What a player was doing when the bot shut down.
+#[derive(Serialize, Deserialize)]
+struct SavedPlayer {
+	voice_channel_id: u64,
+	text_channel_id: u64,
+	paused: bool,
+	/// Players saved without one keep the volume stored for the guild, which joining applies.
+	#[serde(default)]
+	volume: Option<u16>,
+	#[serde(default)]
+	filters: Option<Filters>,
+	/// Of the current song, in milliseconds.
+	position: Option<u64>,
+	/// Starts with the current song.
+	queue: Vec<QueuedTrack>,
+}
+
+/// Writes every player to the database, so `restore_players` can continue them after a restart.
+pub async fn save_players(data: &Arc<RwLock<TypeMap>>) {
+	let (backend, songbird, database) = {
+		let data = data.read().await;
+
+		let backend = match data.get::<Backend>() {
+			Some(backend) => backend.clone(),
+			None => return,
+		};
+
+		(
+			backend,
+			data.get::<SongbirdKey>().unwrap().clone(),
+			data.get::<Database>().unwrap().clone(),
+		)
+	};
+
+	let mut states = Vec::new();
+
+	for guild_id in backend.players().await {
+		let voice_channel_id = match songbird.get(guild_id) {
+			Some(call) => call.lock().await.current_channel(),
+			None => None,
+		};
+
+		let voice_channel_id = match voice_channel_id {
+			Some(channel_id) => channel_id.0,
+			None => continue,
+		};
+
+		let player_data = match backend.data(guild_id).await {
+			Some(player_data) => player_data,
+			None => continue,
+		};
+
+		let (text_channel_id, filters) = {
+			let player_data = player_data.read().await;
+
+			match player_data.get::<CallerChannel>() {
+				Some(caller_channel) => (caller_channel.channel_id.0, player_data.get::<Filters>().cloned()),
+				None => continue,
+			}
+		};
+
+		let now_playing = backend.now_playing(guild_id).await;
+
+		let player = SavedPlayer {
+			voice_channel_id,
+			text_channel_id,
+			paused: now_playing.as_ref().is_some_and(|now_playing| now_playing.paused),
+			volume: backend.volume(guild_id).await,
+			filters,
+			position: now_playing.as_ref().map(|now_playing| now_playing.position),
+			queue: now_playing
+				.map(|now_playing| now_playing.track)
+				.into_iter()
+				.chain(backend.queue(guild_id).await)
+				.collect(),
+		};
+
+		match serde_json::to_string(&player) {
+			Ok(state) => states.push((guild_id, state)),
+			Err(why) => log::error!("Could not save the player of guild {}: {:?}", guild_id, why),
+		}
+	}
+
+	match database.save_player_states(&states).await {
+		Ok(()) => log::info!("Saved {} players", states.len()),
+		Err(why) => log::error!("Could not save the players: {:?}", why),
+	}
+}
+
+/// Rejoins the voice channels the bot was in before the last shutdown and continues playing.
+pub async fn restore_players(ctx: &Context) {
+	let (backend, database) = {
+		let data = ctx.data.read().await;
+
+		match data.get::<Backend>() {
+			Some(backend) => (backend.clone(), data.get::<Database>().unwrap().clone()),
+			None => return,
+		}
+	};
+
+	let states = match database.take_player_states().await {
+		Ok(states) => states,
+		Err(why) => {
+			log::error!("Could not load the saved players: {:?}", why);
+
+			return;
+		}
+	};
+
+	for (guild_id, state) in states {
+		match serde_json::from_str::<SavedPlayer>(&state) {
+			Ok(player) => restore_player(ctx, &*backend, guild_id, player).await,
+			Err(why) => log::error!("Could not read the saved player of guild {}: {:?}", guild_id, why),
+		}
+	}
+}
+
+async fn restore_player(ctx: &Context, backend: &dyn MusicBackend, guild_id: GuildId, player: SavedPlayer) {
+	let connected = backend.connect(ctx, guild_id, ChannelId(player.voice_channel_id), ChannelId(player.text_channel_id)).await;
+
+	if let Err(why) = connected {
+		log::error!("Could not rejoin the voice channel of guild {}: {}", guild_id, why);
+
+		return;
+	}
+
+	if let Some(volume) = player.volume {
+		if let Err(why) = backend.set_volume(guild_id, volume).await {
+			log::warn!("Could not restore the volume of guild {}: {:?}", guild_id, why);
+		}
+	}
+
+	// Without a queue nothing resumes that could apply them
+	if player.queue.is_empty() {
+		return;
+	}
+
+	if let (Some(filters), Some(data)) = (player.filters, backend.data(guild_id).await) {
+		data.write().await.insert::<Filters>(filters);
+	}
+
+	if let Err(why) = backend.resume(guild_id, &player.queue, player.position, player.paused).await {
+		log::error!("Could not resume the queue of guild {}: {:?}", guild_id, why);
+
+		return;
+	}
+
+	backend.announce(guild_id, "Back again, continuing where the music stopped").await;
+}
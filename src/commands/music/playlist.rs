@@ -0,0 +1,231 @@
+use serde::{Deserialize, Serialize};
+use serenity::framework::standard::{Args, CommandResult};
+use serenity::framework::standard::macros::command;
+use serenity::model::prelude::{Message, UserId};
+use serenity::prelude::Context;
+
+use crate::commands::music::backend::QueuedTrack;
+use crate::commands::music::dj::may_manage;
+use crate::commands::music::util::music_backend;
+use crate::database::{Database, PlaylistOwner, PlaylistTrack};
+use crate::invocation::Invocation;
+
+const MAX_NAME_LENGTH: usize = 32;
+
+const USAGE: &str = "Use `save <name>`, `load <name>`, `add <name> <link>`, `delete <name>` or `list`, with `--server` before the name for playlists of the server";
+
+/// What is saved about a song of a playlist, named like the track info of lavalink.
+#[derive(Serialize, Deserialize)]
+struct SavedInfo {
+	title: String,
+	uri: String,
+	/// In milliseconds.
+	length: u64,
+	#[serde(rename = "isStream")]
+	is_stream: bool,
+	#[serde(rename = "isSeekable")]
+	is_seekable: bool,
+}
+
+pub enum PlaylistAction {
+	/// Saves the current queue.
+	Save(String),
+	Load(String),
+	/// Adds the songs of a link or the first search result.
+	Add(String, String),
+	Delete(String),
+	List,
+}
+
+#[command]
+#[aliases("playlists", "pl")]
+#[description("Saves the queue as a playlist to load it again later.\nPlaylists are your own unless `--server` is put before the name, those can be loaded by everyone on the server. Only DJs can change them while a DJ role is set.")]
+#[usage("$action $name")]
+#[example("list")]
+#[example("save chill")]
+#[example("load chill")]
+#[example("add chill https://www.youtube.com/watch?v=dQw4w9WgXcQ")]
+#[example("delete --server party")]
+async fn playlist(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+	let action = args.single::<String>().unwrap_or_default().to_lowercase();
+
+	let server = args.current() == Some("--server");
+	if server {
+		args.advance();
+	}
+
+	let name = args.single::<String>().ok();
+	let link = args.rest().to_string();
+
+	let action = match (action.as_str(), name) {
+		("list", _) => Some(PlaylistAction::List),
+		("save", Some(name)) => Some(PlaylistAction::Save(name)),
+		("load", Some(name)) => Some(PlaylistAction::Load(name)),
+		("delete" | "remove", Some(name)) => Some(PlaylistAction::Delete(name)),
+		("add", Some(name)) if !link.is_empty() => Some(PlaylistAction::Add(name, link)),
+		_ => None,
+	};
+
+	match action {
+		Some(action) => handle_playlist(ctx, &msg.into(), action, server).await,
+		None => {
+			msg.reply(&ctx.http, USAGE).await?;
+
+			Ok(())
+		}
+	}
+}
+
+pub async fn handle_playlist(ctx: &Context, invocation: &Invocation<'_>, action: PlaylistAction, server: bool) -> CommandResult {
+	let guild_id = invocation.guild_id().unwrap();
+	let user_id = invocation.author().id;
+	let database = ctx.data.read().await.get::<Database>().unwrap().clone();
+
+	let owner = if server {
+		PlaylistOwner::Guild(guild_id)
+	} else {
+		PlaylistOwner::User(user_id)
+	};
+
+	let changes = matches!(action, PlaylistAction::Save(_) | PlaylistAction::Add(..) | PlaylistAction::Delete(_));
+	if server && changes && !may_manage(ctx, guild_id, user_id).await {
+		invocation.reply(ctx, "Only DJs can change the playlists of this server").await?;
+
+		return Ok(());
+	}
+
+	match action {
+		PlaylistAction::List => {
+			let own = database.playlists(PlaylistOwner::User(user_id)).await?;
+			let guild = database.playlists(PlaylistOwner::Guild(guild_id)).await?;
+
+			invocation.send_embed(ctx, |embed| {
+				embed.title("Playlists")
+					.field("Yours", list_playlists(&own), false)
+					.field("This server's", list_playlists(&guild), false)
+			}).await?;
+		}
+		PlaylistAction::Save(name) => {
+			if name.chars().count() > MAX_NAME_LENGTH {
+				invocation.reply(ctx, format!("The name can be at most {} characters long", MAX_NAME_LENGTH)).await?;
+
+				return Ok(());
+			}
+
+			let backend = music_backend(ctx, invocation).await?;
+
+			let current = backend.now_playing(guild_id).await.map(|now_playing| now_playing.track);
+
+			let tracks = current
+				.iter()
+				.chain(&backend.queue(guild_id).await)
+				.filter_map(to_playlist_track)
+				.collect::<Vec<_>>();
+
+			if tracks.is_empty() {
+				invocation.say(ctx, "The queue is empty, there is nothing to save").await?;
+
+				return Ok(());
+			}
+
+			database.save_playlist(owner, &name, &tracks).await?;
+			invocation.say(ctx, format!("Saved {} songs as `{}`", tracks.len(), name)).await?;
+		}
+		PlaylistAction::Load(name) => {
+			let tracks = database.playlist(owner, &name).await?;
+
+			if tracks.is_empty() {
+				invocation.reply(ctx, format!("There is no playlist called `{}`", name)).await?;
+
+				return Ok(());
+			}
+
+			let manager = songbird::get(ctx).await.unwrap().clone();
+			if manager.get(guild_id).is_none() {
+				invocation.say(ctx, "Use `join` first, to connect the bot to your current voice channel.").await?;
+
+				return Ok(());
+			}
+
+			let backend = music_backend(ctx, invocation).await?;
+
+			let tracks = tracks.iter().filter_map(|saved| from_playlist_track(saved, user_id)).collect::<Vec<_>>();
+			let added = backend.enqueue_tracks(guild_id, &tracks).await?;
+
+			invocation.say(ctx, format!("Added {} songs from `{}`", added, name)).await?;
+		}
+		PlaylistAction::Add(name, link) => {
+			if name.chars().count() > MAX_NAME_LENGTH {
+				invocation.reply(ctx, format!("The name can be at most {} characters long", MAX_NAME_LENGTH)).await?;
+
+				return Ok(());
+			}
+
+			let backend = music_backend(ctx, invocation).await?;
+
+			let tracks = backend.search(guild_id, &link, 1).await?
+				.iter()
+				.filter_map(to_playlist_track)
+				.collect::<Vec<_>>();
+
+			if tracks.is_empty() {
+				invocation.say(ctx, "Could not find any video of the search query.").await?;
+
+				return Ok(());
+			}
+
+			database.add_to_playlist(owner, &name, &tracks).await?;
+			invocation.say(ctx, format!("Added {} songs to `{}`", tracks.len(), name)).await?;
+		}
+		PlaylistAction::Delete(name) => {
+			if database.delete_playlist(owner, &name).await? {
+				invocation.say(ctx, format!("Deleted `{}`", name)).await?;
+			} else {
+				invocation.reply(ctx, format!("There is no playlist called `{}`", name)).await?;
+			}
+		}
+	}
+
+	Ok(())
+}
+
+fn to_playlist_track(track: &QueuedTrack) -> Option<PlaylistTrack> {
+	let info = SavedInfo {
+		title: track.title.clone(),
+		uri: track.uri.clone()?,
+		length: track.length,
+		is_stream: track.is_stream,
+		is_seekable: track.is_seekable,
+	};
+
+	Some(PlaylistTrack {
+		track: track.id.clone(),
+		info: serde_json::to_string(&info).ok()?,
+	})
+}
+
+fn from_playlist_track(saved: &PlaylistTrack, requester: UserId) -> Option<QueuedTrack> {
+	let info = serde_json::from_str::<SavedInfo>(&saved.info).ok()?;
+
+	Some(QueuedTrack {
+		id: saved.track.clone(),
+		title: info.title,
+		uri: Some(info.uri),
+		length: info.length,
+		is_stream: info.is_stream,
+		is_seekable: info.is_seekable,
+		requester: Some(requester),
+	})
+}
+
+fn list_playlists(playlists: &[(String, usize)]) -> String {
+	if playlists.is_empty() {
+		return "None yet".to_string();
+	}
+
+	playlists
+		.iter()
+		.map(|(name, songs)| format!("`{}` ({} songs)", name, songs))
+		.collect::<Vec<_>>()
+		.join("\n")
+}
@@ -1,5 +1,9 @@
 use std::cmp::max;
+use std::time::Duration;
 
+use std::sync::Arc;
+
+use serenity::builder::CreateComponents;
 use serenity::{
 	framework::{
 		standard::{
@@ -9,54 +13,189 @@ use serenity::{
 	},
 	model::{channel::Message},
 };
-use serenity::prelude::Context;
+use serenity::model::id::GuildId;
+use serenity::model::interactions::InteractionResponseType;
+use serenity::model::interactions::message_component::ButtonStyle;
+use serenity::prelude::{Context, Mentionable};
+
+use crate::commands::music::backend::{MusicBackend, NowPlaying, QueuedTrack};
+use crate::commands::music::export::{handle_export, handle_import};
+use crate::commands::music::util::{format_millis, loop_mode, music_backend, LoopMode};
+use crate::invocation::Invocation;
 
-use crate::commands::music::handlers::Lavalink;
+const PAGE_SIZE: usize = 15;
+/// How long the page buttons keep working after the last click.
+const PAGE_TIMEOUT: Duration = Duration::from_secs(60);
 
 #[command]
-#[description("Displays the songs that are currently queued, split up into pages of 15 songs per page.")]
+#[description("Displays the songs that are currently queued and how long they take to play, split up into pages of 15 songs per page that can be flipped through with the buttons below.\n`export` uploads the queue as a JSON or M3U file, `import` adds the songs of such a file attached to the message.")]
 #[usage("$page")]
 #[example("")]
 #[example("2")]
+#[example("export m3u")]
+#[example("import")]
 async fn queue(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
-	let data = ctx.data.read().await;
-	let lava_client = data.get::<Lavalink>().unwrap().clone();
-	let guild_id = u64::from(msg.guild_id.unwrap());
+	match args.current().map(str::to_lowercase).as_deref() {
+		Some("export") => {
+			args.advance();
+
+			return match args.current().unwrap_or("json").parse() {
+				Ok(format) => handle_export(ctx, &msg.into(), format).await,
+				Err(()) => {
+					msg.reply(&ctx.http, "Unknown format, use `json` or `m3u`").await?;
+
+					Ok(())
+				}
+			};
+		}
+		Some("import") => {
+			return match msg.attachments.first() {
+				Some(attachment) => handle_import(ctx, &msg.into(), attachment).await,
+				None => {
+					msg.reply(&ctx.http, "Attach a JSON or M3U file exported with `queue export`").await?;
+
+					Ok(())
+				}
+			};
+		}
+		_ => {}
+	}
 
-	let page = if args.len() < 1 {
+	let page = if args.is_empty() {
 		1
 	} else {
 		let page_number = args.single::<usize>()?;
 		max(page_number, 1)
 	};
 
+	handle_queue(ctx, &msg.into(), page).await
+}
+
+pub async fn handle_queue(ctx: &Context, invocation: &Invocation<'_>, page: usize) -> CommandResult {
+	let backend = music_backend(ctx, invocation).await?;
+	let guild_id = invocation.guild_id().unwrap();
+
+	let mut page = queue_page(&backend, guild_id, page).await;
+
+	if page.count <= 1 {
+		invocation.send_embed(ctx, |embed| {
+			embed.description(&page.content)
+		}).await?;
+
+		return Ok(());
+	}
+
+	let mut message = invocation.send_embed_with_components(
+		ctx,
+		|embed| embed.description(&page.content),
+		|components| page_buttons(components, &page),
+	).await?;
+
+	// Only whoever asked for the queue can browse it, everyone else can use the command themselves
+	while let Some(interaction) = message
+		.await_component_interaction(ctx)
+		.author_id(invocation.author().id)
+		.timeout(PAGE_TIMEOUT)
+		.await
+	{
+		let number = match interaction.data.custom_id.as_str() {
+			"first" => 1,
+			"previous" => page.number.saturating_sub(1),
+			"next" => page.number + 1,
+			"last" => page.count,
+			_ => page.number,
+		};
+
+		// The queue might have changed since the last page was shown
+		page = queue_page(&backend, guild_id, number).await;
+
+		interaction.create_interaction_response(&ctx.http, |response| {
+			response
+				.kind(InteractionResponseType::UpdateMessage)
+				.interaction_response_data(|data| {
+					data.create_embed(|embed| embed.description(&page.content))
+						.components(|components| page_buttons(components, &page))
+				})
+		}).await?;
+	}
+
+	invocation.remove_components(ctx, &mut message).await?;
+
+	Ok(())
+}
+
+/// A page of the queue as it is shown in the embed.
+struct QueuePage {
+	content: String,
+	/// Starts at 1.
+	number: usize,
+	count: usize,
+}
+
+/// Renders the page of the queue, the number is clamped to the pages there are.
+async fn queue_page(backend: &Arc<dyn MusicBackend>, guild_id: GuildId, number: usize) -> QueuePage {
+	let loop_mode = match backend.data(guild_id).await {
+		Some(data) => loop_mode(&data).await,
+		None => LoopMode::Off,
+	};
+	let now_playing = backend.now_playing(guild_id).await;
+	// The current song isn't shown
+	let queue = backend.queue(guild_id).await;
+
+	let count = queue.len().div_ceil(PAGE_SIZE);
+	let number = number.clamp(1, count.max(1));
 
-	let mut page_content = String::new();
-	if let Some(node) = lava_client.nodes().await.get(&guild_id) {
-		let queue = &node.queue;
-		if queue.len() > 1 {
-			for i in ((15 * (page - 1)) + 1)..((15 * page) + 1) { //Iterate over the vector in slices of 15 elements
-				if i >= queue.len() { break; } //Stop the loop at the end of the Vector
-				page_content.push_str(&format!("{} . {}\n", i, queue[i].track.info.as_ref().unwrap().title))
+	let mut content = String::new();
+	if !queue.is_empty() {
+		for (i, track) in queue.iter().enumerate().skip(PAGE_SIZE * (number - 1)).take(PAGE_SIZE) {
+			content.push_str(&format!("{} . {}", i + 1, track.title));
+			if let Some(requester) = track.requester {
+				content.push_str(&format!(" ({})", requester.mention()));
 			}
-			page_content.push_str(&format!(
-				"\n\nPage {} of {} ({} songs total)",
-				page,
-				(((queue.len() - 1) as f64) / 15.0).ceil(),
-				queue.len()
-			)
-			)
-		} else {
-			page_content = "Queue is empty".to_string();
+			content.push('\n');
 		}
-	};
+		content.push_str(&format!(
+			"\n\nPage {} of {} ({} songs total, {} remaining)",
+			number,
+			count,
+			queue.len() + 1,
+			format_millis(remaining(now_playing.as_ref(), &queue)),
+		));
+	} else {
+		content = "Queue is empty".to_string();
+	}
 
-	msg.channel_id.send_message(&ctx.http, |msg| {
-		msg.embed(|embed| {
-			embed.description(page_content)
-		})
-	}).await?;
+	match loop_mode {
+		LoopMode::Off => {}
+		LoopMode::Track => content.push_str("\nLooping the current song"),
+		LoopMode::Queue => content.push_str("\nLooping the queue"),
+	}
 
+	QueuePage { content, number, count }
+}
 
-	Ok(())
-}
\ No newline at end of file
+/// How long it takes to play the rest of the queue in milliseconds, live streams don't count.
+fn remaining(now_playing: Option<&NowPlaying>, queue: &[QueuedTrack]) -> u64 {
+	let current = now_playing
+		.filter(|now_playing| !now_playing.track.is_stream)
+		.map_or(0, |now_playing| now_playing.track.length.saturating_sub(now_playing.position));
+
+	current + queue
+		.iter()
+		.filter(|track| !track.is_stream)
+		.map(|track| track.length)
+		.sum::<u64>()
+}
+
+fn page_buttons<'a>(components: &'a mut CreateComponents, page: &QueuePage) -> &'a mut CreateComponents {
+	let first = page.number <= 1;
+	let last = page.number >= page.count;
+
+	components.create_action_row(|row| {
+		row
+			.create_button(|button| button.style(ButtonStyle::Secondary).label("First").custom_id("first").disabled(first))
+			.create_button(|button| button.style(ButtonStyle::Primary).label("Previous").custom_id("previous").disabled(first))
+			.create_button(|button| button.style(ButtonStyle::Primary).label("Next").custom_id("next").disabled(last))
+			.create_button(|button| button.style(ButtonStyle::Secondary).label("Last").custom_id("last").disabled(last))
+	})
+}
@@ -1,35 +1,242 @@
+use std::collections::{BTreeSet, HashSet};
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
 use serenity::framework::standard::{Args, CommandResult};
 use serenity::framework::standard::macros::command;
-use serenity::model::prelude::Message;
+use serenity::model::prelude::{Message, UserId};
 use serenity::prelude::Context;
+use serenity::utils::parse_username;
+
+use crate::commands::music::backend::QueuedTrack;
+use crate::commands::music::dj::DJ_CHECK;
+use crate::commands::music::util::music_backend;
+use crate::invocation::Invocation;
+
+/// Embed descriptions can be at most 4096 characters long.
+const MAX_SUMMARY_LENGTH: usize = 4000;
+
+/// Which songs to take out of the queue.
+pub enum Removal {
+	/// Queue positions as shown by the queue command, a single position is a range of one.
+	Positions(Vec<RangeInclusive<usize>>),
+	/// Every song the user requested.
+	User(UserId),
+	/// Every song that is already in the queue further up.
+	Dupes,
+}
+
+impl FromStr for Removal {
+	type Err = ();
+
+	fn from_str(songs: &str) -> Result<Self, Self::Err> {
+		let songs = songs.trim();
+
+		if songs.eq_ignore_ascii_case("dupes") || songs.eq_ignore_ascii_case("duplicates") {
+			return Ok(Removal::Dupes);
+		}
+
+		if let Some(user_id) = parse_username(songs) {
+			return Ok(Removal::User(UserId(user_id)));
+		}
+
+		let positions = songs
+			.split(|c: char| c.is_whitespace() || c == ',')
+			.filter(|position| !position.is_empty())
+			.map(|position| match position.split_once('-') {
+				Some((start, end)) => match (start.parse::<usize>(), end.parse::<usize>()) {
+					(Ok(start), Ok(end)) if start <= end => Ok(start..=end),
+					_ => Err(()),
+				},
+				None => position.parse::<usize>().map(|position| position..=position).map_err(|_| ()),
+			})
+			.collect::<Result<Vec<_>, _>>()?;
+
+		if positions.is_empty() {
+			return Err(());
+		}
+
+		Ok(Removal::Positions(positions))
+	}
+}
 
-use crate::commands::music::handlers::Lavalink;
+impl Removal {
+	/// The indexes of the songs in the queue after the current song (see [`MusicBackend::queue`]), in order.
+	/// `None` if a position is not in the queue.
+	///
+	/// [`MusicBackend::queue`]: crate::commands::music::backend::MusicBackend::queue
+	pub fn indexes(&self, queue: &[QueuedTrack]) -> Option<Vec<usize>> {
+		match self {
+			Removal::Positions(positions) => {
+				let mut indexes = BTreeSet::new();
+
+				for range in positions {
+					// Positions start at 1, like in the queue command
+					if *range.start() < 1 || *range.end() > queue.len() {
+						return None;
+					}
+
+					indexes.extend(range.clone().map(|position| position - 1));
+				}
+
+				Some(indexes.into_iter().collect())
+			}
+			Removal::User(user_id) => Some(
+				(0..queue.len())
+					.filter(|index| queue[*index].requester == Some(*user_id))
+					.collect()
+			),
+			Removal::Dupes => {
+				let mut seen = HashSet::new();
+
+				Some(
+					(0..queue.len())
+						.filter(|index| !seen.insert(queue[*index].uri.as_ref().unwrap_or(&queue[*index].id)))
+						.collect()
+				)
+			}
+		}
+	}
+}
 
 #[command]
-#[num_args(1)]
-#[description("Removes a song from the queue.\nIt is recommended to first use the queue command to view the queued songs and their indexes.")]
-#[usage("$queue_index")]
+#[checks(DJ)]
+#[min_args(1)]
+#[description("Removes songs from the queue.\nTakes queue positions (see the queue command), ranges of them, a user to remove all of their songs or `dupes` to remove songs that are queued more than once.")]
+#[usage("$songs")]
 #[example("7")]
-#[example("4")]
-async fn remove(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
-	let data = ctx.data.read().await;
-	let lava_client = data.get::<Lavalink>().unwrap().clone();
-
-	if let Some(mut node) = lava_client.nodes().await.get_mut(msg.guild_id.unwrap().as_u64()) {
-		let queue = &mut node.queue;
-		if let Ok(index) = args.single::<usize>() {
-			if index > queue.len() || index < 1 {
-				msg.channel_id.say(&ctx.http, "Invalid queue index").await?;
-			} else {
-				let removed_track = queue.remove(index);
-				msg.channel_id.say(
-					&ctx.http,
-					format!("Removed track `{}` from the queue", removed_track.track.info.unwrap().title),
-				).await?;
-			}
+#[example("3-8")]
+#[example("2 5 9")]
+#[example("@user")]
+#[example("dupes")]
+async fn remove(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+	match args.rest().parse() {
+		Ok(removal) => handle_remove(ctx, &msg.into(), removal).await,
+		Err(()) => {
+			msg.channel_id.say(&ctx.http, "Invalid queue index").await?;
+
+			Ok(())
+		}
+	}
+}
+
+pub async fn handle_remove(ctx: &Context, invocation: &Invocation<'_>, removal: Removal) -> CommandResult {
+	let backend = music_backend(ctx, invocation).await?;
+	let guild_id = invocation.guild_id().unwrap();
+
+	let removed = match backend.remove(guild_id, &removal).await? {
+		Some(removed) => removed,
+		None => {
+			invocation.say(ctx, "Invalid queue index").await?;
+
+			return Ok(());
 		}
+	};
+
+	if removed.is_empty() {
+		invocation.say(ctx, "No songs to remove").await?;
+
+		return Ok(());
 	}
 
+	let mut summary = String::new();
+	for (shown, (index, track)) in removed.iter().enumerate() {
+		let line = format!("{} . {}\n", index + 1, track.title);
+
+		if summary.len() + line.len() > MAX_SUMMARY_LENGTH {
+			summary.push_str(&format!("and {} more", removed.len() - shown));
+			break;
+		}
+
+		summary.push_str(&line);
+	}
+
+	let title = match removed.len() {
+		1 => "Removed 1 song from the queue".to_string(),
+		count => format!("Removed {} songs from the queue", count),
+	};
+
+	invocation.send_embed(ctx, |embed| {
+		embed.title(title).description(summary)
+	}).await?;
 
 	Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A queued song that is only told apart by its link.
+	fn song(uri: &str, requester: u64) -> QueuedTrack {
+		QueuedTrack {
+			id: format!("{}#{}", uri, requester),
+			title: uri.to_string(),
+			uri: Some(uri.to_string()),
+			length: 0,
+			is_stream: false,
+			is_seekable: true,
+			requester: Some(UserId(requester)),
+		}
+	}
+
+	fn positions(songs: &str) -> Option<Vec<RangeInclusive<usize>>> {
+		match songs.parse() {
+			Ok(Removal::Positions(positions)) => Some(positions),
+			_ => None,
+		}
+	}
+
+	#[test]
+	fn parses_positions_ranges_and_lists() {
+		assert_eq!(positions("7"), Some(vec![7..=7]));
+		assert_eq!(positions(" 3-8 "), Some(vec![3..=8]));
+		assert_eq!(positions("2 5,9, 1-3"), Some(vec![2..=2, 5..=5, 9..=9, 1..=3]));
+	}
+
+	#[test]
+	fn parses_users_and_dupes() {
+		assert!(matches!("<@!123>".parse(), Ok(Removal::User(UserId(123)))));
+		assert!(matches!("Duplicates".parse(), Ok(Removal::Dupes)));
+	}
+
+	#[test]
+	fn rejects_reversed_ranges_and_garbage() {
+		assert_eq!(positions("8-3"), None);
+		assert_eq!(positions("3-"), None);
+		assert_eq!(positions("1-2-3"), None);
+		assert_eq!(positions(","), None);
+		assert!("@user".parse::<Removal>().is_err());
+	}
+
+	#[test]
+	fn turns_positions_into_indexes() {
+		let queue = [song("a", 1), song("b", 1), song("c", 1)];
+
+		assert_eq!(Removal::Positions(vec![3..=3, 1..=2, 2..=2]).indexes(&queue), Some(vec![0, 1, 2]));
+	}
+
+	#[test]
+	fn rejects_positions_outside_the_queue() {
+		let queue = [song("a", 1), song("b", 1)];
+
+		assert_eq!(Removal::Positions(vec![0..=1]).indexes(&queue), None);
+		assert_eq!(Removal::Positions(vec![2..=3]).indexes(&queue), None);
+		assert_eq!(Removal::Positions(vec![1..=1]).indexes(&[]), None);
+	}
+
+	#[test]
+	fn finds_the_songs_of_a_user() {
+		let queue = [song("a", 1), song("b", 2), song("c", 1)];
+
+		assert_eq!(Removal::User(UserId(1)).indexes(&queue), Some(vec![0, 2]));
+		assert_eq!(Removal::User(UserId(3)).indexes(&queue), Some(vec![]));
+	}
+
+	#[test]
+	fn keeps_the_first_of_duplicates() {
+		let queue = [song("a", 1), song("b", 1), song("a", 2), song("b", 2), song("c", 2)];
+
+		assert_eq!(Removal::Dupes.indexes(&queue), Some(vec![2, 3]));
+	}
+}
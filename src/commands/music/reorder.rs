@@ -0,0 +1,78 @@
+use serenity::framework::standard::{Args, CommandResult};
+use serenity::framework::standard::macros::command;
+use serenity::model::prelude::Message;
+use serenity::prelude::Context;
+
+use crate::commands::music::dj::DJ_CHECK;
+use crate::commands::music::util::music_backend;
+use crate::invocation::Invocation;
+
+#[command("move")]
+#[num_args(2)]
+#[description("Moves a song to another position in the queue.\nThe positions are the ones shown by the queue command.")]
+#[usage("$from $to")]
+#[example("5 1")]
+#[example("1 3")]
+async fn move_track(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+	match (args.single::<usize>(), args.single::<usize>()) {
+		(Ok(from), Ok(to)) => handle_move(ctx, &msg.into(), from, to).await,
+		_ => {
+			msg.channel_id.say(&ctx.http, "Invalid queue index").await?;
+
+			Ok(())
+		}
+	}
+}
+
+#[command]
+#[checks(DJ)]
+#[num_args(1)]
+#[aliases("jump")]
+#[description("Skips to a song in the queue, the songs before it are removed.\nThe position is the one shown by the queue command.")]
+#[usage("$queue_index")]
+#[example("4")]
+async fn skipto(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+	match args.single::<usize>() {
+		Ok(index) => handle_skipto(ctx, &msg.into(), index).await,
+		Err(_) => {
+			msg.channel_id.say(&ctx.http, "Invalid queue index").await?;
+
+			Ok(())
+		}
+	}
+}
+
+pub async fn handle_move(ctx: &Context, invocation: &Invocation<'_>, from: usize, to: usize) -> CommandResult {
+	let backend = music_backend(ctx, invocation).await?;
+	let guild_id = invocation.guild_id().unwrap();
+
+	// The positions shown by the queue command start at 1
+	let moved = match (from.checked_sub(1), to.checked_sub(1)) {
+		(Some(from), Some(to)) => backend.move_track(guild_id, from, to).await?,
+		_ => None,
+	};
+
+	match moved {
+		Some(track) => invocation.say(ctx, format!("Moved `{}` to position {}", track.title, to)).await?,
+		None => invocation.say(ctx, "Invalid queue index").await?,
+	};
+
+	Ok(())
+}
+
+pub async fn handle_skipto(ctx: &Context, invocation: &Invocation<'_>, index: usize) -> CommandResult {
+	let backend = music_backend(ctx, invocation).await?;
+	let guild_id = invocation.guild_id().unwrap();
+
+	let track = match index.checked_sub(1) {
+		Some(index) => backend.skip_to(guild_id, index).await?,
+		None => None,
+	};
+
+	match track {
+		Some(track) => invocation.say(ctx, format!("Skipped to `{}`", track.title)).await?,
+		None => invocation.say(ctx, "Invalid queue index").await?,
+	};
+
+	Ok(())
+}
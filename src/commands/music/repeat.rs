@@ -0,0 +1,58 @@
+use serenity::framework::standard::{Args, CommandResult};
+use serenity::framework::standard::macros::command;
+use serenity::model::prelude::Message;
+use serenity::prelude::Context;
+
+use crate::commands::music::util::{loop_mode, LoopMode, music_backend};
+use crate::invocation::Invocation;
+
+#[command("loop")]
+#[aliases("repeat")]
+#[description("Shows or changes what happens to songs after they finished playing.\n`off` removes them from the queue, `track` plays the current song again and `queue` puts finished songs back at the end of the queue.")]
+#[usage("$mode")]
+#[example("")]
+#[example("track")]
+#[example("off")]
+#[max_args(1)]
+async fn repeat(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+	let mode = if args.is_empty() {
+		None
+	} else {
+		match args.rest().parse() {
+			Ok(mode) => Some(mode),
+			Err(()) => {
+				msg.reply(&ctx.http, "Unknown loop mode, use `off`, `track` or `queue`").await?;
+
+				return Ok(());
+			}
+		}
+	};
+
+	handle_loop(ctx, &msg.into(), mode).await
+}
+
+pub async fn handle_loop(ctx: &Context, invocation: &Invocation<'_>, mode: Option<LoopMode>) -> CommandResult {
+	let backend = music_backend(ctx, invocation).await?;
+	let guild_id = invocation.guild_id().unwrap();
+
+	let data = match backend.data(guild_id).await {
+		Some(data) => data,
+		None => {
+			invocation.reply(ctx, "Not in a channel").await?;
+
+			return Ok(());
+		}
+	};
+
+	match mode {
+		Some(mode) => {
+			backend.set_loop_mode(guild_id, mode).await;
+			invocation.say(ctx, format!("Set the loop mode to `{}`", mode)).await?;
+		}
+		None => {
+			invocation.say(ctx, format!("The loop mode is `{}`", loop_mode(&data).await)).await?;
+		}
+	}
+
+	Ok(())
+}
@@ -3,18 +3,22 @@ use serenity::framework::standard::macros::command;
 use serenity::model::prelude::Message;
 use serenity::prelude::Context;
 
-use crate::commands::music::handlers::Lavalink;
+use crate::commands::music::util::music_backend;
+use crate::invocation::Invocation;
 
 #[command]
 #[aliases("resume")]
 #[description("Unpauses the player.")]
 async fn unpause(ctx: &Context, msg: &Message) -> CommandResult {
-	let data = ctx.data.read().await;
-	let lava_client = data.get::<Lavalink>().unwrap().clone();
-	let guild_id = u64::from(msg.guild_id.unwrap());
+	handle_unpause(ctx, &msg.into()).await
+}
 
-	lava_client.resume(guild_id).await?;
-	msg.channel_id.say(&ctx.http, "Unpaused player").await?;
+pub async fn handle_unpause(ctx: &Context, invocation: &Invocation<'_>) -> CommandResult {
+	let backend = music_backend(ctx, invocation).await?;
+
+	backend.set_paused(invocation.guild_id().unwrap(), false).await?;
+
+	invocation.say(ctx, "Unpaused player").await?;
 
 	Ok(())
 }
\ No newline at end of file
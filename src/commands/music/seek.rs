@@ -0,0 +1,105 @@
+use serenity::framework::standard::{Args, CommandResult};
+use serenity::framework::standard::macros::command;
+use serenity::model::prelude::Message;
+use serenity::prelude::Context;
+
+use crate::commands::music::util::{format_millis, music_backend, parse_time};
+use crate::invocation::Invocation;
+
+#[command]
+#[description("Jumps to a position in the current song.")]
+#[usage("$time")]
+#[example("1:23")]
+#[example("1:02:03")]
+#[example("90s")]
+#[min_args(1)]
+async fn seek(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+	handle_seek(ctx, &msg.into(), args.rest()).await
+}
+
+#[command]
+#[aliases("ff", "fastforward")]
+#[description("Skips ahead in the current song.")]
+#[usage("$time")]
+#[example("30s")]
+#[example("2m")]
+#[min_args(1)]
+async fn forward(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+	handle_forward(ctx, &msg.into(), args.rest()).await
+}
+
+#[command]
+#[aliases("rw")]
+#[description("Goes back in the current song.")]
+#[usage("$time")]
+#[example("10s")]
+#[example("1:00")]
+#[min_args(1)]
+async fn rewind(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+	handle_rewind(ctx, &msg.into(), args.rest()).await
+}
+
+pub async fn handle_seek(ctx: &Context, invocation: &Invocation<'_>, time: &str) -> CommandResult {
+	jump(ctx, invocation, time, |time, _| Some(time)).await
+}
+
+pub async fn handle_forward(ctx: &Context, invocation: &Invocation<'_>, time: &str) -> CommandResult {
+	jump(ctx, invocation, time, |time, position| position.checked_add(time)).await
+}
+
+pub async fn handle_rewind(ctx: &Context, invocation: &Invocation<'_>, time: &str) -> CommandResult {
+	jump(ctx, invocation, time, |time, position| Some(position.saturating_sub(time))).await
+}
+
+/// Seeks to the position `target` calculates from the given time and the current position,
+/// all in milliseconds.
+async fn jump(
+	ctx: &Context,
+	invocation: &Invocation<'_>,
+	time: &str,
+	target: impl FnOnce(u64, u64) -> Option<u64>,
+) -> CommandResult {
+	let time = match parse_time(time) {
+		Some(time) => time,
+		None => {
+			invocation.reply(ctx, "Invalid time, use something like `1:23`, `90s` or `2m`").await?;
+
+			return Ok(());
+		}
+	};
+
+	let backend = music_backend(ctx, invocation).await?;
+	let guild_id = invocation.guild_id().unwrap();
+
+	let now_playing = match backend.now_playing(guild_id).await {
+		Some(now_playing) => now_playing,
+		None => {
+			invocation.say(ctx, "Nothing is playing at the moment.").await?;
+
+			return Ok(());
+		}
+	};
+
+	if !now_playing.track.is_seekable {
+		invocation.reply(ctx, "Can't jump around in this song").await?;
+
+		return Ok(());
+	}
+
+	let length = now_playing.track.length;
+
+	let position = match target(time, now_playing.position) {
+		Some(position) if position < length => position,
+		_ => {
+			invocation.reply(ctx, format!("The song is only {} long", format_millis(length))).await?;
+
+			return Ok(());
+		}
+	};
+
+	backend.seek(guild_id, position).await?;
+
+	invocation.say(ctx, format!("Jumped to {} of {}", format_millis(position), format_millis(length))).await?;
+
+	Ok(())
+}
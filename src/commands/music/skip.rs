@@ -1,32 +1,112 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
 use serenity::framework::standard::CommandResult;
 use serenity::framework::standard::macros::command;
-use serenity::model::prelude::Message;
-use serenity::prelude::Context;
+use serenity::model::prelude::{Message, UserId};
+use serenity::prelude::{Context, TypeMapKey};
+
+use crate::commands::music::backend::{MusicBackend, QueuedTrack};
+use crate::commands::music::dj::{is_dj, DJ_CHECK};
+use crate::commands::music::util::{listeners, music_backend};
+use crate::config::ConfigData;
+use crate::invocation::Invocation;
+
+/// Who voted to skip the current track.
+///
+/// Cleared whenever a track starts or ends, the same song played again starts without votes.
+pub struct SkipVotes;
+
+impl TypeMapKey for SkipVotes {
+	type Value = HashSet<UserId>;
+}
 
-use crate::commands::music::handlers::Lavalink;
+enum Vote {
+	Passed,
+	Pending { votes: usize, required: usize },
+	NotListening,
+}
 
 #[command]
-#[description("Skips the currently running song")]
+#[checks(DJ)]
+#[description("Skips the currently running song.\nIf other people are listening, they have to agree by also using skip. DJs and whoever requested the song can skip right away.")]
 async fn skip(ctx: &Context, msg: &Message) -> CommandResult {
-	let data = ctx.data.read().await;
-	let lava_client = data.get::<Lavalink>().unwrap().clone();
-	let guild_id = msg.guild_id.unwrap();
-
-	if let Some(track) = lava_client.skip(msg.guild_id.unwrap()).await {
-		if let Some(node) = lava_client.nodes().await.get(guild_id.as_u64()) {
-			if node.queue.len() == 0 {
-				lava_client.stop(guild_id).await?;
+	handle_skip(ctx, &msg.into()).await
+}
+
+pub async fn handle_skip(ctx: &Context, invocation: &Invocation<'_>) -> CommandResult {
+	let backend = music_backend(ctx, invocation).await?;
+	let guild_id = invocation.guild_id().unwrap();
+
+	if let Some(now_playing) = backend.now_playing(guild_id).await {
+		match vote(ctx, invocation, &backend, &now_playing.track).await {
+			Vote::Passed => {}
+			Vote::Pending { votes, required } => {
+				invocation.say(ctx, format!("Voted to skip, {}/{} votes", votes, required)).await?;
+
+				return Ok(());
+			}
+			Vote::NotListening => {
+				invocation.reply(ctx, "Only people listening in my voice channel can vote to skip").await?;
+
+				return Ok(());
 			}
 		}
-		log::debug!("Skipped: {}", track.track.info.as_ref().unwrap().title);
-		msg.channel_id
+	}
+
+	if let Some(track) = backend.skip(guild_id).await? {
+		log::debug!("Skipped: {}", track.title);
+		invocation
 			.say(
 				ctx,
-				format!("Skipped: {}", track.track.info.as_ref().unwrap().title),
+				format!("Skipped: {}", track.title),
 			)
 			.await?;
 	}
 
-
 	Ok(())
-}
\ No newline at end of file
+}
+
+/// DJs, whoever requested the track and everyone when vote skipping is turned off skip right away.
+/// Everyone else adds their vote, the track is skipped once enough listeners voted for it.
+async fn vote(ctx: &Context, invocation: &Invocation<'_>, backend: &Arc<dyn MusicBackend>, track: &QueuedTrack) -> Vote {
+	let guild_id = invocation.guild_id().unwrap();
+	let user_id = invocation.author().id;
+
+	let percentage = ctx.data.read().await
+		.get::<ConfigData>().unwrap()
+		.music.as_ref()
+		.map_or(0, |music| music.vote_skip_percentage as usize);
+
+	if percentage == 0 || track.requester == Some(user_id) || is_dj(ctx, guild_id, user_id).await {
+		return Vote::Passed;
+	}
+
+	let listeners = listeners(ctx, guild_id).await;
+
+	if !listeners.contains(&user_id) {
+		return Vote::NotListening;
+	}
+
+	let data = match backend.data(guild_id).await {
+		Some(data) => data,
+		None => return Vote::Passed,
+	};
+
+	let mut data = data.write().await;
+	let voters = data.entry::<SkipVotes>().or_insert_with(HashSet::new);
+
+	voters.insert(user_id);
+
+	// Votes of people who left the channel don't count anymore
+	let votes = voters.iter().filter(|voter| listeners.contains(voter)).count();
+	let required = (listeners.len() * percentage).div_ceil(100).max(1);
+
+	if votes >= required {
+		voters.clear();
+
+		Vote::Passed
+	} else {
+		Vote::Pending { votes, required }
+	}
+}
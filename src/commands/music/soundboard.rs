@@ -0,0 +1,256 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serenity::framework::standard::{Args, CommandResult};
+use serenity::framework::standard::macros::command;
+use serenity::model::channel::Attachment;
+use serenity::model::prelude::{GuildId, Message};
+use serenity::prelude::{Context, TypeMapKey};
+
+use crate::commands::music::backend::ClipPlayback;
+use crate::commands::music::dj::may_manage;
+use crate::commands::music::local::has_audio_extension;
+use crate::commands::music::util::music_backend;
+use crate::config::ConfigData;
+use crate::invocation::Invocation;
+
+const MAX_NAME_LENGTH: usize = 32;
+/// Clips are meant to be short, this is about a minute of mp3.
+const MAX_CLIP_SIZE: u64 = 1024 * 1024;
+/// Can't be used as names, the subcommands would be run instead.
+const RESERVED_NAMES: &[&str] = &["list", "add", "remove", "delete"];
+
+const USAGE: &str = "Use `<name>` to play a clip, `list`, `add <name>` with an audio file attached or `remove <name>`";
+
+/// The soundboard clip that is interrupting the queue right now, kept in the player data.
+pub struct PlayingClip {
+	/// Tells the clip apart from the songs of the queue.
+	pub track: String,
+	/// Whether the player was paused before the clip, it is paused again afterwards.
+	pub paused: bool,
+}
+
+impl TypeMapKey for PlayingClip {
+	type Value = PlayingClip;
+}
+
+pub enum SoundboardAction {
+	Play(String),
+	List,
+	Remove(String),
+}
+
+#[command]
+#[aliases("sb")]
+#[description("Plays a short clip of the server's soundboard right away, the current song continues afterwards.\n`list` shows the clips, `add` saves the audio file attached to the message as a new clip and `remove` deletes one. Only DJs can change the clips while a DJ role is set.")]
+#[usage("$name")]
+#[example("airhorn")]
+#[example("list")]
+#[example("add airhorn")]
+#[example("remove airhorn")]
+async fn soundboard(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+	let first = args.single::<String>().unwrap_or_default().to_lowercase();
+	let name = args.single::<String>().ok().map(|name| name.to_lowercase());
+
+	let action = match (first.as_str(), name) {
+		("", _) => None,
+		("list", _) => Some(SoundboardAction::List),
+		("add", Some(name)) => {
+			return match msg.attachments.first() {
+				Some(attachment) => handle_soundboard_add(ctx, &msg.into(), name, attachment).await,
+				None => {
+					msg.reply(&ctx.http, "Attach the audio file of the clip to the message").await?;
+
+					Ok(())
+				}
+			};
+		}
+		("remove" | "delete", Some(name)) => Some(SoundboardAction::Remove(name)),
+		(name, None) if !RESERVED_NAMES.contains(&name) => Some(SoundboardAction::Play(name.to_string())),
+		_ => None,
+	};
+
+	match action {
+		Some(action) => handle_soundboard(ctx, &msg.into(), action).await,
+		None => {
+			msg.reply(&ctx.http, USAGE).await?;
+
+			Ok(())
+		}
+	}
+}
+
+pub async fn handle_soundboard(ctx: &Context, invocation: &Invocation<'_>, action: SoundboardAction) -> CommandResult {
+	let guild_id = invocation.guild_id().unwrap();
+	let directory = clip_directory(ctx, guild_id).await;
+
+	match action {
+		SoundboardAction::List => {
+			let names = clips(&directory).await?.into_iter().map(|(name, _)| name).collect::<Vec<_>>();
+
+			if names.is_empty() {
+				invocation.say(ctx, "This server has no clips yet, add one with `soundboard add <name>`").await?;
+
+				return Ok(());
+			}
+
+			invocation.send_embed(ctx, |embed| {
+				embed.title("Soundboard").description(names.iter().map(|name| format!("`{}`", name)).collect::<Vec<_>>().join(", "))
+			}).await?;
+		}
+		SoundboardAction::Play(name) => {
+			let manager = songbird::get(ctx).await.unwrap().clone();
+			if manager.get(guild_id).is_none() {
+				invocation.say(ctx, "Use `join` first, to connect the bot to your current voice channel.").await?;
+
+				return Ok(());
+			}
+
+			let path = match clip(&directory, &name).await? {
+				Some(path) => path,
+				None => {
+					invocation.reply(ctx, format!("There is no clip called `{}`", name)).await?;
+
+					return Ok(());
+				}
+			};
+
+			let backend = music_backend(ctx, invocation).await?;
+
+			match backend.play_clip(guild_id, &path, invocation.author().id).await? {
+				// The clip speaks for itself
+				ClipPlayback::Started => log::debug!("Playing clip {} in guild {}", name, guild_id),
+				ClipPlayback::AlreadyPlaying => {
+					invocation.reply(ctx, "Another clip is playing, wait until it is over").await?;
+				}
+				ClipPlayback::Failed => {
+					invocation.say(ctx, format!("Could not play `{}`", name)).await?;
+				}
+			}
+		}
+		SoundboardAction::Remove(name) => {
+			if !may_manage(ctx, guild_id, invocation.author().id).await {
+				invocation.reply(ctx, "Only DJs can change the soundboard of this server").await?;
+
+				return Ok(());
+			}
+
+			match clip(&directory, &name).await? {
+				Some(path) => {
+					tokio::fs::remove_file(path).await?;
+					invocation.say(ctx, format!("Removed `{}` from the soundboard", name)).await?;
+				}
+				None => {
+					invocation.reply(ctx, format!("There is no clip called `{}`", name)).await?;
+				}
+			}
+		}
+	}
+
+	Ok(())
+}
+
+/// Saves the attached audio file as a clip of the server, replacing an older clip with the same name.
+pub async fn handle_soundboard_add(ctx: &Context, invocation: &Invocation<'_>, name: String, attachment: &Attachment) -> CommandResult {
+	let guild_id = invocation.guild_id().unwrap();
+
+	if !may_manage(ctx, guild_id, invocation.author().id).await {
+		invocation.reply(ctx, "Only DJs can change the soundboard of this server").await?;
+
+		return Ok(());
+	}
+
+	// The name becomes the file name, so it may only contain characters that are safe in paths
+	let valid = !name.is_empty()
+		&& name.chars().count() <= MAX_NAME_LENGTH
+		&& name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+		&& !RESERVED_NAMES.contains(&name.as_str());
+
+	if !valid {
+		invocation.reply(ctx, format!(
+			"Names can only contain letters, numbers, `-` and `_`, can be at most {} characters long and can't be {}",
+			MAX_NAME_LENGTH,
+			RESERVED_NAMES.iter().map(|name| format!("`{}`", name)).collect::<Vec<_>>().join(", "),
+		)).await?;
+
+		return Ok(());
+	}
+
+	let extension = Path::new(&attachment.filename)
+		.extension()
+		.and_then(|extension| extension.to_str())
+		.map(str::to_lowercase);
+
+	let extension = match extension {
+		Some(extension) if has_audio_extension(Path::new(&attachment.filename)) => extension,
+		_ => {
+			invocation.reply(ctx, "The file has to be audio, like mp3, ogg or wav").await?;
+
+			return Ok(());
+		}
+	};
+
+	if attachment.size > MAX_CLIP_SIZE {
+		invocation.reply(ctx, format!("Clips can be at most {} KB", MAX_CLIP_SIZE / 1024)).await?;
+
+		return Ok(());
+	}
+
+	let content = attachment.download().await?;
+	let directory = clip_directory(ctx, guild_id).await;
+
+	tokio::fs::create_dir_all(&directory).await?;
+
+	// An older clip with the same name might be another kind of file
+	if let Some(old) = clip(&directory, &name).await? {
+		tokio::fs::remove_file(old).await?;
+	}
+
+	tokio::fs::write(directory.join(format!("{}.{}", name, extension)), content).await?;
+
+	invocation.say(ctx, format!("Added `{}` to the soundboard", name)).await?;
+
+	Ok(())
+}
+
+/// Where the clips of the guild are stored, every guild has its own directory.
+async fn clip_directory(ctx: &Context, guild_id: GuildId) -> PathBuf {
+	let data = ctx.data.read().await;
+	let music = data.get::<ConfigData>().unwrap().music.as_ref().unwrap();
+
+	Path::new(&music.soundboard_directory).join(guild_id.0.to_string())
+}
+
+/// The names and files of all clips in the directory, sorted by name.
+async fn clips(directory: &Path) -> io::Result<Vec<(String, PathBuf)>> {
+	let mut entries = match tokio::fs::read_dir(directory).await {
+		Ok(entries) => entries,
+		// Created once the first clip is added
+		Err(why) if why.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+		Err(why) => return Err(why),
+	};
+
+	let mut clips = Vec::new();
+
+	while let Some(entry) = entries.next_entry().await? {
+		let path = entry.path();
+
+		if let (Some(name), true) = (path.file_stem().and_then(|name| name.to_str()), has_audio_extension(&path)) {
+			clips.push((name.to_string(), path.clone()));
+		}
+	}
+
+	clips.sort();
+
+	Ok(clips)
+}
+
+/// The file of the clip, with an absolute path since lavalink doesn't know where the bot was started.
+async fn clip(directory: &Path, name: &str) -> io::Result<Option<PathBuf>> {
+	let path = clips(directory).await?.into_iter().find(|(clip, _)| clip == name).map(|(_, path)| path);
+
+	match path {
+		Some(path) => Ok(Some(tokio::fs::canonicalize(path).await?)),
+		None => Ok(None),
+	}
+}
@@ -1,10 +1,18 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 use std::sync::Arc;
 
 use chrono::Duration;
-use lavalink_rs::model::Node;
+use lavalink_rs::LavalinkClient;
+use lavalink_rs::model::{Node, TrackQueue};
+use serenity::framework::standard::CommandError;
 use serenity::http::Http;
-use serenity::model::prelude::ChannelId;
-use serenity::prelude::TypeMapKey;
+use serenity::model::prelude::{ChannelId, GuildId, UserId};
+use serenity::prelude::{Context, RwLock, TypeMap, TypeMapKey};
+
+use crate::commands::music::backend::{announce_in, Backend, MusicBackend};
+use crate::commands::music::handlers::Lavalink;
+use crate::invocation::Invocation;
 
 pub struct CallerChannel {
 	pub(crate) channel_id: ChannelId,
@@ -15,6 +23,59 @@ impl TypeMapKey for CallerChannel {
 	type Value = CallerChannel;
 }
 
+/// What happens to a track after it finished playing.
+#[derive(Clone, Copy, PartialEq)]
+pub enum LoopMode {
+	/// The track is removed from the queue.
+	Off,
+	/// The track is played again.
+	Track,
+	/// The track is put back at the end of the queue.
+	Queue,
+}
+
+impl TypeMapKey for LoopMode {
+	type Value = LoopMode;
+}
+
+impl Display for LoopMode {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			LoopMode::Off => write!(f, "off"),
+			LoopMode::Track => write!(f, "track"),
+			LoopMode::Queue => write!(f, "queue"),
+		}
+	}
+}
+
+impl FromStr for LoopMode {
+	type Err = ();
+
+	fn from_str(mode: &str) -> Result<Self, Self::Err> {
+		match mode.to_lowercase().as_str() {
+			"off" | "none" => Ok(LoopMode::Off),
+			"track" | "song" | "one" => Ok(LoopMode::Track),
+			"queue" | "all" => Ok(LoopMode::Queue),
+			_ => Err(()),
+		}
+	}
+}
+
+/// The loop mode kept in the player data.
+pub async fn loop_mode(data: &RwLock<TypeMap>) -> LoopMode {
+	data.read().await.get::<LoopMode>().copied().unwrap_or(LoopMode::Off)
+}
+
+/// The track that is currently playing.
+///
+/// lavalink-rs already forgot it by the time `track_finish` is called,
+/// so it is kept here to be able to queue it again.
+pub struct PlayingTrack;
+
+impl TypeMapKey for PlayingTrack {
+	type Value = TrackQueue;
+}
+
 pub async fn set_caller_channel(node: &Node, http: Arc<Http>, channel_id: ChannelId) {
 	let mut typemap = node.data.write().await;
 	let caller_channel = CallerChannel {
@@ -24,12 +85,200 @@ pub async fn set_caller_channel(node: &Node, http: Arc<Http>, channel_id: Channe
 	typemap.insert::<CallerChannel>(caller_channel);
 }
 
+/// Posts a message in the channel music was last requested from.
+pub async fn announce(lava_client: &LavalinkClient, guild_id: GuildId, content: &str) {
+	let data = match lava_client.nodes().await.get(&guild_id.0) {
+		Some(node) => Arc::clone(&node.data),
+		None => return,
+	};
+
+	announce_in(&data, guild_id, content).await;
+}
+
+/// The backend the guild plays on.
+/// Tells the user and returns an error if it can't play right now.
+pub async fn music_backend(ctx: &Context, invocation: &Invocation<'_>) -> Result<Arc<dyn MusicBackend>, CommandError> {
+	let backend = ctx.data.read().await.get::<Backend>().unwrap().clone();
+
+	if backend.is_available(invocation.guild_id().unwrap()).await {
+		Ok(backend)
+	} else {
+		invocation.say(ctx, "None of the music servers are reachable right now, try again later").await?;
+
+		Err("No lavalink node is connected".into())
+	}
+}
+
+/// The client of the lavalink node the guild plays on.
+/// Tells the user and returns an error if no node is connected or music isn't played through lavalink.
+pub async fn lavalink_client(ctx: &Context, invocation: &Invocation<'_>) -> Result<LavalinkClient, CommandError> {
+	let lavalink_nodes = match ctx.data.read().await.get::<Lavalink>() {
+		Some(lavalink_nodes) => lavalink_nodes.clone(),
+		None => {
+			invocation.say(ctx, "This only works when music is played through lavalink").await?;
+
+			return Err("The lavalink backend is not in use".into());
+		}
+	};
+
+	match lavalink_nodes.client(invocation.guild_id().unwrap()).await {
+		Some(client) => Ok(client),
+		None => {
+			invocation.say(ctx, "None of the music servers are reachable right now, try again later").await?;
+
+			Err("No lavalink node is connected".into())
+		}
+	}
+}
+
+/// Who asked for the track, if anyone did.
+pub fn requester(track: &TrackQueue) -> Option<UserId> {
+	track.requester.map(|requester| UserId(requester.0))
+}
+
+/// Everyone except bots in the voice channel the bot is in.
+pub async fn listeners(ctx: &Context, guild_id: GuildId) -> Vec<UserId> {
+	let guild = match guild_id.to_guild_cached(&ctx.cache).await {
+		Some(guild) => guild,
+		None => return Vec::new(),
+	};
+
+	let bot_id = ctx.cache.current_user_id().await;
+
+	let channel_id = match guild.voice_states.get(&bot_id).and_then(|voice_state| voice_state.channel_id) {
+		Some(channel_id) => channel_id,
+		None => return Vec::new(),
+	};
+
+	guild.voice_states
+		.values()
+		.filter(|voice_state| voice_state.channel_id == Some(channel_id))
+		.filter(|voice_state| !guild.members.get(&voice_state.user_id).is_some_and(|member| member.user.bot))
+		.map(|voice_state| voice_state.user_id)
+		.collect()
+}
+
 pub fn format_millis(millis: u64) -> String {
 	let duration = Duration::milliseconds(millis as i64);
 	format!("{:02}:{:02}:{:02}", duration.num_hours(), duration.num_minutes() % 60, duration.num_seconds() % 60)
 }
 
+/// Parses times like `1:23`, `1:02:03`, `90s`, `2m` or `1m30s` into milliseconds.
+/// A plain number is read as seconds.
+pub fn parse_time(time: &str) -> Option<u64> {
+	let time = time.split_whitespace().collect::<String>();
+
+	if time.is_empty() {
+		return None;
+	}
+
+	if time.contains(':') {
+		let parts = time
+			.split(':')
+			.map(|part| part.parse::<u64>().ok())
+			.collect::<Option<Vec<_>>>()?;
+
+		// Everything after the first part has to be below 60, `1:75` is most likely a typo
+		if parts.len() > 3 || parts.iter().skip(1).any(|part| *part >= 60) {
+			return None;
+		}
+
+		let seconds = parts
+			.iter()
+			.try_fold(0u64, |total, part| total.checked_mul(60)?.checked_add(*part))?;
+
+		return seconds.checked_mul(1000);
+	}
+
+	if let Ok(seconds) = time.parse::<u64>() {
+		return seconds.checked_mul(1000);
+	}
+
+	let mut millis = 0u64;
+	let mut number = String::new();
+
+	for c in time.chars() {
+		if c.is_ascii_digit() {
+			number.push(c);
+			continue;
+		}
+
+		let unit = match c.to_ascii_lowercase() {
+			'h' => 60 * 60 * 1000,
+			'm' => 60 * 1000,
+			's' => 1000,
+			_ => return None,
+		};
+
+		let value = number.parse::<u64>().ok()?;
+		number.clear();
+		millis = millis.checked_add(value.checked_mul(unit)?)?;
+	}
+
+	// A number without a unit at the end, like `1m30`
+	if !number.is_empty() {
+		return None;
+	}
+
+	Some(millis)
+}
+
 pub fn is_link(link: &str) -> bool {
 	link.starts_with("https://")
 		|| link.starts_with("http://")
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+	use super::parse_time;
+
+	#[test]
+	fn parses_minutes_and_seconds() {
+		assert_eq!(parse_time("1:23"), Some(83_000));
+		assert_eq!(parse_time("0:05"), Some(5_000));
+		// Only the first part may go beyond 59
+		assert_eq!(parse_time("75:00"), Some(4_500_000));
+	}
+
+	#[test]
+	fn parses_hours_minutes_and_seconds() {
+		assert_eq!(parse_time("1:02:03"), Some(3_723_000));
+	}
+
+	#[test]
+	fn parses_plain_seconds() {
+		assert_eq!(parse_time("90"), Some(90_000));
+		assert_eq!(parse_time(" 0 "), Some(0));
+	}
+
+	#[test]
+	fn parses_units() {
+		assert_eq!(parse_time("90s"), Some(90_000));
+		assert_eq!(parse_time("2m"), Some(120_000));
+		assert_eq!(parse_time("1m30s"), Some(90_000));
+		assert_eq!(parse_time("1H 2M 3S"), Some(3_723_000));
+	}
+
+	#[test]
+	fn rejects_out_of_range_parts() {
+		assert_eq!(parse_time("1:60"), None);
+		assert_eq!(parse_time("1:00:75"), None);
+		assert_eq!(parse_time("1:2:3:4"), None);
+	}
+
+	#[test]
+	fn rejects_empty_times() {
+		assert_eq!(parse_time(""), None);
+		assert_eq!(parse_time("   "), None);
+	}
+
+	#[test]
+	fn rejects_garbage() {
+		assert_eq!(parse_time("soon"), None);
+		assert_eq!(parse_time("-10"), None);
+		assert_eq!(parse_time("1.5"), None);
+		assert_eq!(parse_time(":30"), None);
+		// A number needs its unit
+		assert_eq!(parse_time("1m30"), None);
+	}
+}
@@ -0,0 +1,80 @@
+use lavalink_rs::LavalinkClient;
+use lavalink_rs::model::LavalinkResult;
+use serenity::framework::standard::{Args, CommandResult};
+use serenity::framework::standard::macros::command;
+use serenity::model::prelude::{GuildId, Message};
+use serenity::prelude::Context;
+
+use crate::commands::music::util::music_backend;
+use crate::database::Database;
+use crate::invocation::Invocation;
+
+/// The volume of new players, lavalink uses the same default.
+pub const DEFAULT_VOLUME: u16 = 100;
+pub const MAX_VOLUME: u16 = 200;
+
+#[command]
+#[aliases("vol")]
+#[description("Shows or changes the volume of the player in percent, from 0 to 200.\nThe volume is remembered for the next time the bot joins.")]
+#[usage("$volume")]
+#[example("")]
+#[example("50")]
+#[max_args(1)]
+async fn volume(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+	if args.is_empty() {
+		return handle_volume(ctx, &msg.into(), None).await;
+	}
+
+	match args.single::<u16>() {
+		Ok(volume) => handle_volume(ctx, &msg.into(), Some(volume)).await,
+		Err(_) => {
+			msg.reply(&ctx.http, format!("The volume has to be a number from 0 to {}", MAX_VOLUME)).await?;
+
+			Ok(())
+		}
+	}
+}
+
+pub async fn handle_volume(ctx: &Context, invocation: &Invocation<'_>, volume: Option<u16>) -> CommandResult {
+	let guild_id = invocation.guild_id().unwrap();
+	let database = ctx.data.read().await.get::<Database>().unwrap().clone();
+
+	let volume = match volume {
+		Some(volume) => volume,
+		None => {
+			let volume = database.guild_volume(guild_id).await?.unwrap_or(DEFAULT_VOLUME);
+			invocation.say(ctx, format!("The volume is {}%", volume)).await?;
+
+			return Ok(());
+		}
+	};
+
+	if volume > MAX_VOLUME {
+		invocation.reply(ctx, format!("The volume has to be a number from 0 to {}", MAX_VOLUME)).await?;
+
+		return Ok(());
+	}
+
+	database.set_guild_volume(guild_id, volume).await?;
+
+	// Without a session the volume is applied the next time the bot joins
+	let backend = music_backend(ctx, invocation).await?;
+	if backend.data(guild_id).await.is_some() {
+		backend.set_volume(guild_id, volume).await?;
+	}
+
+	invocation.say(ctx, format!("Set the volume to {}%", volume)).await?;
+
+	Ok(())
+}
+
+/// Sets the volume of the player and remembers it on the node, so it survives moving the player.
+pub async fn apply_volume(lava_client: &LavalinkClient, guild_id: GuildId, volume: u16) -> LavalinkResult<()> {
+	lava_client.volume(guild_id, volume).await?;
+
+	if let Some(mut node) = lava_client.nodes().await.get_mut(&guild_id.0) {
+		node.volume = volume;
+	}
+
+	Ok(())
+}
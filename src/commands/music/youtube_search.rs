@@ -1,71 +1,141 @@
+use std::time::Duration;
+
 use serenity::framework::standard::{Args, CommandResult};
 use serenity::framework::standard::macros::command;
+use serenity::model::interactions::InteractionResponseType;
+use serenity::model::interactions::message_component::ButtonStyle;
 use serenity::model::prelude::Message;
 use serenity::prelude::Context;
 
-use crate::commands::music::handlers::Lavalink;
+use crate::commands::music::backend::QueuedTrack;
+use crate::commands::music::util::{format_millis, music_backend};
+use crate::invocation::Invocation;
+
+/// How many results can be picked from, discord allows at most 5 buttons in a row.
+const RESULTS: usize = 5;
+const PICK_TIMEOUT: Duration = Duration::from_secs(30);
 
 #[command]
 #[aliases("search", "youtube")]
-#[description("Searches for a song on youtube. If it found a track it will add it to the queue and start the player if it is not running")]
+#[description("Searches for a song on youtube and lets you pick one of the top results to add to the queue, starts the player if it is not running.\nWith `--first` the first result is added right away.")]
 #[usage("$search_query")]
 #[example("Rammstein Rosenrot")]
+#[example("--first Rammstein Rosenrot")]
 async fn youtube_search(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
-	let query = args.message().to_string();
+	let query = args.message();
 
-	let guild_id = match ctx.cache.guild_channel(msg.channel_id).await {
-		Some(channel) => channel.guild_id,
-		None => {
-			msg.channel_id
-				.say(&ctx.http, "Error finding channel info")
-				.await?;
+	match query.strip_prefix("--first") {
+		Some(query) => handle_youtube_search(ctx, &msg.into(), query.trim_start(), true).await,
+		None => handle_youtube_search(ctx, &msg.into(), query, false).await,
+	}
+}
 
-			return Ok(());
-		}
-	};
+pub async fn handle_youtube_search(ctx: &Context, invocation: &Invocation<'_>, query: &str, first: bool) -> CommandResult {
+	let guild_id = invocation.guild_id().unwrap();
 
 	let manager = songbird::get(ctx).await.unwrap().clone();
 
 	if let Some(_handler) = manager.get(guild_id) {
-		let data = ctx.data.read().await;
-		let lava_client = data.get::<Lavalink>().unwrap().clone();
+		let backend = music_backend(ctx, invocation).await?;
 
-		let query_information = lava_client.auto_search_tracks(&query).await?;
+		let results = backend.search(guild_id, query, RESULTS).await?;
 
-		if query_information.tracks.is_empty() {
-			msg.channel_id
-				.say(&ctx, "Could not find any video of the search query.")
+		if results.is_empty() {
+			invocation
+				.say(ctx, "Could not find any video of the search query.")
 				.await?;
 			return Ok(());
 		}
 
-		let track = &query_information.tracks[0];
-		if let Err(why) =
+		let track = if first || results.len() == 1 {
+			&results[0]
+		} else {
+			let description = results
+				.iter()
+				.enumerate()
+				.map(|(index, track)| format!("{}. {} ({})", index + 1, track.title, format_millis(track.length)))
+				.collect::<Vec<_>>()
+				.join("\n");
 
-		&lava_client.play(guild_id, track.clone())
-			// Change this to play() if you want your own custom queue or no queue at all.
-			.queue()
-			.await
-		{
-			log::error!("An error occurred: {:?}", why);
-			return Ok(());
+			let mut message = invocation.send_embed_with_components(
+				ctx,
+				|embed| {
+					embed.title("Search results")
+						.description(description)
+						.footer(|footer| footer.text(format!("Pick a song within {} seconds", PICK_TIMEOUT.as_secs())))
+				},
+				|components| {
+					components
+						.create_action_row(|row| {
+							for index in 0..results.len() {
+								row.create_button(|button| {
+									button.style(ButtonStyle::Primary).label(index + 1).custom_id(index)
+								});
+							}
+
+							row
+						})
+						.create_action_row(|row| {
+							row.create_button(|button| button.style(ButtonStyle::Secondary).label("Cancel").custom_id("cancel"))
+						})
+				},
+			).await?;
+
+			let interaction = message
+				.await_component_interaction(ctx)
+				.author_id(invocation.author().id)
+				.timeout(PICK_TIMEOUT)
+				.await;
+
+			let interaction = match interaction {
+				Some(interaction) => interaction,
+				None => {
+					invocation.remove_components(ctx, &mut message).await?;
+					invocation.say(ctx, "No song was picked in time").await?;
+
+					return Ok(());
+				}
+			};
+
+			// Acknowledges the click and removes the buttons, so nothing can be picked twice
+			interaction.create_interaction_response(&ctx.http, |response| {
+				response
+					.kind(InteractionResponseType::UpdateMessage)
+					.interaction_response_data(|data| data.components(|components| components))
+			}).await?;
+
+			match interaction.data.custom_id.parse::<usize>().ok().and_then(|index| results.get(index)) {
+				Some(track) => track,
+				None => {
+					invocation.say(ctx, "Cancelled the search").await?;
+
+					return Ok(());
+				}
+			}
 		};
 
+		let track = QueuedTrack { requester: Some(invocation.author().id), ..track.clone() };
+
+		if backend.enqueue_tracks(guild_id, std::slice::from_ref(&track)).await? == 0 {
+			invocation.say(ctx, format!("Could not play `{}`", track.title)).await?;
+
+			return Ok(());
+		}
 
-		msg.channel_id
+		invocation
 			.say(
-				&ctx.http,
-				format!("Added track `{}`", track.info.as_ref().unwrap().title),
+				ctx,
+				format!("Added track `{}`", track.title),
 			)
 			.await?;
 	} else {
-		msg.channel_id
+		invocation
 			.say(
-				&ctx.http,
+				ctx,
 				"Use `join` first, to connect the bot to your current voice channel.",
 			)
 			.await?;
 	}
 
 	Ok(())
-}
\ No newline at end of file
+}
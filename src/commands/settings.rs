@@ -0,0 +1,44 @@
+use serenity::framework::standard::{Args, CommandResult, macros::command};
+use serenity::model::prelude::Message;
+use serenity::prelude::Context;
+
+use crate::config::ConfigData;
+use crate::database::Database;
+
+const MAX_PREFIX_LENGTH: usize = 10;
+
+#[command]
+#[description("Shows or changes the command prefix of this server.\nUse `reset` to go back to the default prefix.")]
+#[usage("$new_prefix")]
+#[example("")]
+#[example("!")]
+#[example("reset")]
+#[max_args(1)]
+async fn prefix(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+	let guild_id = msg.guild_id.unwrap();
+	let data = ctx.data.read().await;
+	let database = data.get::<Database>().unwrap();
+	let default_prefix = &data.get::<ConfigData>().unwrap().general.prefix;
+
+	if args.is_empty() {
+		let prefix = database.guild_prefix(guild_id).await?
+			.unwrap_or_else(|| default_prefix.clone());
+		msg.channel_id.say(&ctx.http, format!("The prefix of this server is `{}`", prefix)).await?;
+
+		return Ok(());
+	}
+
+	let new_prefix = args.rest();
+
+	if new_prefix == "reset" {
+		database.set_guild_prefix(guild_id, None).await?;
+		msg.channel_id.say(&ctx.http, format!("Reset the prefix to `{}`", default_prefix)).await?;
+	} else if new_prefix.chars().count() > MAX_PREFIX_LENGTH {
+		msg.reply(&ctx.http, format!("The prefix can be at most {} characters long", MAX_PREFIX_LENGTH)).await?;
+	} else {
+		database.set_guild_prefix(guild_id, Some(new_prefix)).await?;
+		msg.channel_id.say(&ctx.http, format!("Changed the prefix to `{}`", new_prefix)).await?;
+	}
+
+	Ok(())
+}
@@ -1,12 +1,19 @@
-use std::fs::File;
-use std::io::Read;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::{env, fs, io};
 
 use serde::Deserialize;
 use serenity::prelude::TypeMapKey;
+use toml::Value;
+
+const DEFAULT_CONFIG_PATH: &str = "./config.toml";
+const CONFIG_PATH_ENV: &str = "LEDERMANN_CONFIG";
+const CONFIG_PATH_FLAG: &str = "--config";
 
 #[derive(Deserialize)]
 pub struct ConfigData {
 	pub general: General,
+	#[cfg_attr(not(feature = "music"), allow(dead_code))]
 	pub music: Option<Music>,
 }
 
@@ -15,27 +22,565 @@ pub struct General {
 	pub token: String,
 	pub prefix: String,
 	pub invite_url: String,
+	#[serde(default = "default_database")]
+	pub database: String,
 }
 
 #[derive(Deserialize)]
+#[cfg_attr(not(feature = "music"), allow(dead_code))]
 pub struct Music {
-	pub lavalink_password: String,
+	/// What plays the music, lavalink by default.
+	#[serde(default)]
+	pub backend: BackendKind,
+	/// Also used by every entry of `nodes` that doesn't set its own password.
+	pub lavalink_password: Option<String>,
+	#[serde(default = "default_lavalink_host")]
+	pub lavalink_host: String,
+	#[serde(default = "default_lavalink_port")]
+	pub lavalink_port: u16,
+	/// Whether to connect to lavalink with `wss://` instead of `ws://`.
+	#[serde(default)]
+	pub lavalink_ssl: bool,
+	/// The voice region the lavalink server is closest to.
+	pub lavalink_region: Option<String>,
+	/// How many percent of the listeners have to vote to skip a track, 0 turns voting off.
+	#[serde(default = "default_vote_skip_percentage")]
+	pub vote_skip_percentage: u8,
+	/// Seconds until the bot leaves a voice channel nobody listens in anymore, 0 stays forever.
+	#[serde(default = "default_alone_timeout")]
+	pub alone_timeout: u64,
+	/// Minutes until the bot leaves when nothing was played, 0 stays forever.
+	#[serde(default = "default_idle_timeout")]
+	pub idle_timeout: u64,
+	/// The directory the `local` command plays audio files from, the command is off without it.
+	pub media_directory: Option<String>,
+	/// Where the soundboard clips of every server are stored.
+	#[serde(default = "default_soundboard_directory")]
+	pub soundboard_directory: String,
+	/// Replaces the single node of the `lavalink_*` values when not empty.
+	#[serde(default)]
+	nodes: Vec<NodeEntry>,
+}
+
+/// What plays the music.
+#[derive(Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+#[cfg_attr(not(feature = "music"), allow(dead_code))]
+pub enum BackendKind {
+	/// External lavalink (or andesite) servers, see `nodes`.
+	#[default]
+	Lavalink,
+	/// Songbird inside the bot itself. Needs youtube-dl and ffmpeg, but no lavalink server.
+	Songbird,
+}
+
+/// A `[[music.nodes]]` entry as it is written in the config file.
+#[derive(Deserialize)]
+struct NodeEntry {
+	name: Option<String>,
+	host: String,
+	#[serde(default = "default_lavalink_port")]
+	port: u16,
+	#[serde(default)]
+	ssl: bool,
+	password: Option<String>,
+	region: Option<String>,
+}
+
+/// A lavalink (or andesite) server music can be played on.
+#[derive(Clone)]
+#[cfg_attr(not(feature = "music"), allow(dead_code))]
+pub struct LavalinkNode {
+	pub name: String,
+	pub host: String,
+	pub port: u16,
+	pub ssl: bool,
+	pub password: String,
+	/// Guilds in this voice region prefer this node.
+	pub region: Option<String>,
+}
+
+fn default_database() -> String {
+	"./ledermann.sqlite".to_string()
+}
+
+fn default_lavalink_host() -> String {
+	"127.0.0.1".to_string()
+}
+
+fn default_lavalink_port() -> u16 {
+	2333
 }
 
+fn default_vote_skip_percentage() -> u8 {
+	50
+}
+
+fn default_alone_timeout() -> u64 {
+	60
+}
+
+fn default_idle_timeout() -> u64 {
+	10
+}
+
+fn default_soundboard_directory() -> String {
+	"./soundboard".to_string()
+}
 
 impl TypeMapKey for ConfigData {
 	type Value = ConfigData;
 }
 
+impl ConfigData {
+	/// Checks values that deserialize fine but can't work, so they fail at startup instead of later.
+	fn validate(&self) -> Result<(), ConfigError> {
+		if self.general.prefix.is_empty() {
+			return Err(ConfigError::invalid("general.prefix", "must not be empty"));
+		}
+
+		#[cfg(feature = "music")]
+		if self.music.is_none() {
+			return Err(ConfigError::invalid("music", "is required when the music feature is enabled"));
+		}
+
+		if let Some(music) = &self.music {
+			music.validate()?;
+		}
+
+		Ok(())
+	}
+}
+
+impl Music {
+	/// All nodes to connect to, falling back to a single node built from the `lavalink_*` values.
+	#[cfg_attr(not(feature = "music"), allow(dead_code))]
+	pub fn nodes(&self) -> Vec<LavalinkNode> {
+		let password = self.lavalink_password.clone().unwrap_or_default();
+
+		if self.nodes.is_empty() {
+			return vec![LavalinkNode {
+				name: format!("{}:{}", self.lavalink_host, self.lavalink_port),
+				host: self.lavalink_host.clone(),
+				port: self.lavalink_port,
+				ssl: self.lavalink_ssl,
+				password,
+				region: self.lavalink_region.clone(),
+			}];
+		}
+
+		self.nodes
+			.iter()
+			.map(|node| LavalinkNode {
+				name: node.name.clone().unwrap_or_else(|| format!("{}:{}", node.host, node.port)),
+				host: node.host.clone(),
+				port: node.port,
+				ssl: node.ssl,
+				password: node.password.clone().unwrap_or_else(|| password.clone()),
+				region: node.region.clone(),
+			})
+			.collect()
+	}
+
+	fn validate(&self) -> Result<(), ConfigError> {
+		if self.vote_skip_percentage > 100 {
+			return Err(ConfigError::invalid("music.vote_skip_percentage", "must be between 0 and 100"));
+		}
+
+		// The lavalink values are ignored without lavalink
+		if self.backend == BackendKind::Songbird {
+			return Ok(());
+		}
+
+		if self.nodes.is_empty() {
+			if self.lavalink_password.is_none() {
+				return Err(ConfigError::invalid("music.lavalink_password", "is required"));
+			}
+
+			return validate_node("music.lavalink_", &self.lavalink_host, self.lavalink_port, self.lavalink_region.as_deref());
+		}
+
+		for (index, node) in self.nodes.iter().enumerate() {
+			let prefix = format!("music.nodes[{}].", index);
+
+			if node.password.is_none() && self.lavalink_password.is_none() {
+				return Err(ConfigError::invalid(
+					format!("{}password", prefix),
+					"is required unless `music.lavalink_password` is set",
+				));
+			}
+
+			validate_node(&prefix, &node.host, node.port, node.region.as_deref())?;
+		}
+
+		Ok(())
+	}
+}
+
+/// `prefix` is put in front of the key names in errors, so they point to the right place in the config.
+fn validate_node(prefix: &str, host: &str, port: u16, region: Option<&str>) -> Result<(), ConfigError> {
+	if host.is_empty() {
+		return Err(ConfigError::invalid(format!("{}host", prefix), "must not be empty"));
+	}
+
+	if host.contains("://") {
+		return Err(ConfigError::invalid(
+			format!("{}host", prefix),
+			format!("must be a host name without a scheme, use `{}ssl` for TLS", prefix),
+		));
+	}
+
+	if host.contains(|c: char| c.is_whitespace() || c == '/') {
+		return Err(ConfigError::invalid(format!("{}host", prefix), "must be a plain host name or ip address"));
+	}
+
+	if port == 0 {
+		return Err(ConfigError::invalid(format!("{}port", prefix), "must be between 1 and 65535"));
+	}
+
+	if let Some(region) = region {
+		if region.is_empty() || region.contains(char::is_whitespace) {
+			return Err(ConfigError::invalid(format!("{}region", prefix), "must be a voice region like `europe` or `us-east`"));
+		}
+	}
+
+	Ok(())
+}
+
+/// A config value that can also be set through an environment variable or a command line flag.
+///
+/// Command line flags take precedence over environment variables,
+/// which take precedence over the config file.
+struct Override {
+	section: &'static str,
+	key: &'static str,
+	env: &'static str,
+	flag: &'static str,
+	kind: Kind,
+}
+
+/// The type an override is parsed as, environment variables and arguments are always strings.
+#[derive(Clone, Copy)]
+enum Kind {
+	String,
+	Integer,
+	Boolean,
+}
+
+const OVERRIDES: &[Override] = &[
+	Override { section: "general", key: "token", env: "LEDERMANN_TOKEN", flag: "--token", kind: Kind::String },
+	Override { section: "general", key: "prefix", env: "LEDERMANN_PREFIX", flag: "--prefix", kind: Kind::String },
+	Override { section: "general", key: "invite_url", env: "LEDERMANN_INVITE_URL", flag: "--invite-url", kind: Kind::String },
+	Override { section: "general", key: "database", env: "LEDERMANN_DATABASE", flag: "--database", kind: Kind::String },
+	Override { section: "music", key: "backend", env: "LEDERMANN_MUSIC_BACKEND", flag: "--music-backend", kind: Kind::String },
+	Override { section: "music", key: "lavalink_password", env: "LEDERMANN_LAVALINK_PASSWORD", flag: "--lavalink-password", kind: Kind::String },
+	Override { section: "music", key: "lavalink_host", env: "LEDERMANN_LAVALINK_HOST", flag: "--lavalink-host", kind: Kind::String },
+	Override { section: "music", key: "lavalink_port", env: "LEDERMANN_LAVALINK_PORT", flag: "--lavalink-port", kind: Kind::Integer },
+	Override { section: "music", key: "lavalink_ssl", env: "LEDERMANN_LAVALINK_SSL", flag: "--lavalink-ssl", kind: Kind::Boolean },
+	Override { section: "music", key: "lavalink_region", env: "LEDERMANN_LAVALINK_REGION", flag: "--lavalink-region", kind: Kind::String },
+	Override { section: "music", key: "vote_skip_percentage", env: "LEDERMANN_VOTE_SKIP_PERCENTAGE", flag: "--vote-skip-percentage", kind: Kind::Integer },
+	Override { section: "music", key: "alone_timeout", env: "LEDERMANN_ALONE_TIMEOUT", flag: "--alone-timeout", kind: Kind::Integer },
+	Override { section: "music", key: "idle_timeout", env: "LEDERMANN_IDLE_TIMEOUT", flag: "--idle-timeout", kind: Kind::Integer },
+	Override { section: "music", key: "media_directory", env: "LEDERMANN_MEDIA_DIRECTORY", flag: "--media-directory", kind: Kind::String },
+	Override { section: "music", key: "soundboard_directory", env: "LEDERMANN_SOUNDBOARD_DIRECTORY", flag: "--soundboard-directory", kind: Kind::String },
+];
+
+#[derive(Debug)]
+pub enum ConfigError {
+	Io(String, io::Error),
+	Toml(toml::de::Error),
+	Argument(String),
+	Invalid { key: String, reason: String },
+}
+
+impl ConfigError {
+	fn invalid(key: impl ToString, reason: impl ToString) -> Self {
+		ConfigError::Invalid { key: key.to_string(), reason: reason.to_string() }
+	}
+}
+
+impl Display for ConfigError {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			ConfigError::Io(path, why) => write!(f, "Could not read config file '{}': {}", path, why),
+			ConfigError::Toml(why) => write!(f, "Invalid config: {} (see --help for setting values without a config file)", why),
+			ConfigError::Argument(why) => write!(f, "Invalid command line arguments: {}\n\n{}", why, usage()),
+			ConfigError::Invalid { key, reason } => write!(f, "Invalid config value for `{}`: {}", key, reason),
+		}
+	}
+}
+
+impl From<toml::de::Error> for ConfigError {
+	fn from(why: toml::de::Error) -> Self {
+		ConfigError::Toml(why)
+	}
+}
+
+/// Reads the config file and applies the overrides from the environment and command line.
+///
+/// The config file is optional as long as everything required is set through overrides,
+/// unless its path was given explicitly.
+pub fn read_config() -> Result<ConfigData, ConfigError> {
+	// Variables from a `.env` file are treated like any other environment variable
+	dotenv::dotenv().ok();
+
+	let arguments = parse_arguments(env::args().skip(1))?;
+
+	let explicit_path = arguments.get(CONFIG_PATH_FLAG).cloned().or_else(|| env::var(CONFIG_PATH_ENV).ok());
+	let path = explicit_path.clone().unwrap_or_else(|| DEFAULT_CONFIG_PATH.to_string());
+
+	log::debug!("Reading config file '{}'", path);
+	let mut config = match fs::read_to_string(&path) {
+		Ok(content) => toml::from_str(&content)?,
+		Err(why) if why.kind() == io::ErrorKind::NotFound && explicit_path.is_none() => {
+			log::info!("No config file found, only using environment variables and command line arguments");
+			Value::Table(Default::default())
+		}
+		Err(why) => return Err(ConfigError::Io(path, why)),
+	};
+
+	apply_overrides(&mut config, &arguments, |name| env::var(name).ok())?;
+
+	let config: ConfigData = config.try_into()?;
+	config.validate()?;
+
+	Ok(config)
+}
+
+/// Sets the values given as command line arguments or through `env`, which looks up environment variables.
+fn apply_overrides(
+	config: &mut Value,
+	arguments: &HashMap<&'static str, String>,
+	env: impl Fn(&str) -> Option<String>,
+) -> Result<(), ConfigError> {
+	for config_override in OVERRIDES {
+		let value = arguments.get(config_override.flag).cloned()
+			.or_else(|| env(config_override.env));
+
+		if let Some(value) = value {
+			log::debug!("Overriding {}.{}", config_override.section, config_override.key);
+			let value = config_override.parse(value)?;
+			set_value(config, config_override.section, config_override.key, value);
+		}
+	}
+
+	Ok(())
+}
+
+impl Override {
+	fn parse(&self, value: String) -> Result<Value, ConfigError> {
+		let key = format!("{}.{}", self.section, self.key);
+
+		match self.kind {
+			Kind::String => Ok(Value::String(value)),
+			Kind::Integer => value
+				.parse()
+				.map(Value::Integer)
+				.map_err(|_| ConfigError::invalid(key, format!("'{}' is not a number", value))),
+			Kind::Boolean => match value.to_lowercase().as_str() {
+				"true" | "1" | "yes" => Ok(Value::Boolean(true)),
+				"false" | "0" | "no" => Ok(Value::Boolean(false)),
+				_ => Err(ConfigError::invalid(key, format!("'{}' is not one of true/false", value))),
+			},
+		}
+	}
+}
+
+fn set_value(config: &mut Value, section: &str, key: &str, value: Value) {
+	if let Value::Table(config) = config {
+		let section = config
+			.entry(section)
+			.or_insert_with(|| Value::Table(Default::default()));
+
+		if let Value::Table(section) = section {
+			section.insert(key.to_string(), value);
+		}
+	}
+}
+
+/// Parses `--flag value` and `--flag=value` pairs of all known flags.
+fn parse_arguments(arguments: impl Iterator<Item = String>) -> Result<HashMap<&'static str, String>, ConfigError> {
+	let flags = OVERRIDES
+		.iter()
+		.map(|config_override| config_override.flag)
+		.chain(std::iter::once(CONFIG_PATH_FLAG))
+		.collect::<Vec<_>>();
+
+	let mut arguments = arguments;
+	let mut parsed = HashMap::new();
+
+	while let Some(argument) = arguments.next() {
+		if argument == "-h" || argument == "--help" {
+			println!("{}", usage());
+			std::process::exit(0);
+		}
+
+		let (name, inline_value) = match argument.split_once('=') {
+			Some((name, value)) => (name.to_string(), Some(value.to_string())),
+			None => (argument, None),
+		};
+
+		let flag = match flags.iter().find(|flag| **flag == name) {
+			Some(flag) => *flag,
+			None => return Err(ConfigError::Argument(format!("Unknown argument '{}'", name))),
+		};
+
+		let value = match inline_value.or_else(|| arguments.next()) {
+			Some(value) => value,
+			None => return Err(ConfigError::Argument(format!("Missing value for '{}'", flag))),
+		};
+
+		parsed.insert(flag, value);
+	}
+
+	Ok(parsed)
+}
 
-pub fn read_config() -> ConfigData {
-	log::debug!("Reading config file");
-	let mut config_file = File::open("./config.toml").expect("Configuration file not found");
-	let mut config_content = String::new();
-	config_file.read_to_string(&mut config_content).unwrap();
+fn usage() -> String {
+	let mut usage = format!(
+		"Usage: ledermann [OPTIONS]\n\n\
+		Every option can also be set through the environment variable next to it.\n\
+		Options take precedence over environment variables, which take precedence over the config file.\n\n  \
+		{:<22} {:<30} Path of the config file (default: {})\n",
+		CONFIG_PATH_FLAG, CONFIG_PATH_ENV, DEFAULT_CONFIG_PATH,
+	);
 
-	let config_data: ConfigData = toml::from_str(&config_content)
-		.expect("Config file was not a valid TOML file or something was missing");
+	for config_override in OVERRIDES {
+		usage.push_str(&format!(
+			"  {:<22} {:<30} Sets {}.{}\n",
+			config_override.flag, config_override.env, config_override.section, config_override.key,
+		));
+	}
 
-	config_data
-}
\ No newline at end of file
+	usage
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn arguments(arguments: &[&str]) -> Result<HashMap<&'static str, String>, ConfigError> {
+		parse_arguments(arguments.iter().map(|argument| argument.to_string()))
+	}
+
+	#[test]
+	fn parses_separate_and_inline_values() {
+		let parsed = arguments(&["--prefix", "!", "--token=abc=def"]).unwrap();
+
+		assert_eq!(parsed["--prefix"], "!");
+		assert_eq!(parsed["--token"], "abc=def");
+	}
+
+	#[test]
+	fn rejects_unknown_flags() {
+		match arguments(&["--volume", "50"]) {
+			Err(ConfigError::Argument(why)) => assert_eq!(why, "Unknown argument '--volume'"),
+			_ => panic!("--volume is no flag"),
+		}
+	}
+
+	#[test]
+	fn rejects_flags_without_value() {
+		match arguments(&["--prefix"]) {
+			Err(ConfigError::Argument(why)) => assert_eq!(why, "Missing value for '--prefix'"),
+			_ => panic!("--prefix needs a value"),
+		}
+	}
+
+	#[test]
+	fn parses_numbers_and_booleans() {
+		let mut config = Value::Table(Default::default());
+		let arguments = arguments(&["--lavalink-port", "2334", "--lavalink-ssl", "Yes"]).unwrap();
+
+		apply_overrides(&mut config, &arguments, |_| None).unwrap();
+
+		assert_eq!(config["music"]["lavalink_port"].as_integer(), Some(2334));
+		assert_eq!(config["music"]["lavalink_ssl"].as_bool(), Some(true));
+	}
+
+	#[test]
+	fn rejects_bad_numbers() {
+		let mut config = Value::Table(Default::default());
+		let env = |name: &str| if name == "LEDERMANN_LAVALINK_PORT" { Some("twenty".to_string()) } else { None };
+
+		match apply_overrides(&mut config, &HashMap::new(), env) {
+			Err(ConfigError::Invalid { key, reason }) => {
+				assert_eq!(key, "music.lavalink_port");
+				assert_eq!(reason, "'twenty' is not a number");
+			}
+			_ => panic!("twenty is no port"),
+		}
+	}
+
+	#[test]
+	fn arguments_take_precedence_over_environment_over_file() {
+		let mut config = toml::from_str::<Value>("
+			[general]
+			token = 'file'
+			prefix = 'file'
+			invite_url = 'file'
+		").unwrap();
+
+		let arguments = arguments(&["--prefix", "argument"]).unwrap();
+		let env = |name: &str| match name {
+			"LEDERMANN_PREFIX" | "LEDERMANN_TOKEN" => Some("environment".to_string()),
+			_ => None,
+		};
+
+		apply_overrides(&mut config, &arguments, env).unwrap();
+
+		assert_eq!(config["general"]["prefix"].as_str(), Some("argument"));
+		assert_eq!(config["general"]["token"].as_str(), Some("environment"));
+		assert_eq!(config["general"]["invite_url"].as_str(), Some("file"));
+	}
+
+	#[test]
+	fn overrides_work_without_config_file() {
+		let mut config = Value::Table(Default::default());
+
+		apply_overrides(&mut config, &arguments(&["--database", "bot.sqlite"]).unwrap(), |_| None).unwrap();
+
+		assert_eq!(config["general"]["database"].as_str(), Some("bot.sqlite"));
+	}
+
+	#[test]
+	fn rejects_lavalink_hosts_with_scheme() {
+		let config = toml::from_str::<ConfigData>("
+			[general]
+			token = 'token'
+			prefix = '!'
+			invite_url = 'url'
+
+			[music]
+			lavalink_password = 'password'
+			lavalink_host = 'wss://lavalink.example.com'
+		").unwrap();
+
+		assert!(matches!(config.validate(), Err(ConfigError::Invalid { key, .. }) if key == "music.lavalink_host"));
+	}
+
+	#[test]
+	fn rejects_invalid_nodes() {
+		let config = toml::from_str::<ConfigData>("
+			[general]
+			token = 'token'
+			prefix = '!'
+			invite_url = 'url'
+
+			[music]
+			lavalink_password = 'password'
+
+			[[music.nodes]]
+			host = 'lavalink.example.com'
+
+			[[music.nodes]]
+			host = 'lavalink.example.com'
+			region = 'us east'
+		").unwrap();
+
+		match config.validate() {
+			Err(ConfigError::Invalid { key, .. }) => assert_eq!(key, "music.nodes[1].region"),
+			_ => panic!("us east is no voice region"),
+		}
+	}
+}
@@ -3,8 +3,18 @@ use serenity::{
 	model::gateway::Ready,
 	prelude::*,
 };
+use serenity::model::interactions::Interaction;
 use serenity::model::prelude::Guild;
 
+use crate::interaction_processing;
+#[cfg(feature = "music")]
+use {
+	crate::music::auto_leave,
+	crate::music::panel,
+	crate::music::persistence,
+	serenity::model::prelude::{GuildId, VoiceState},
+};
+
 pub struct Handler;
 
 #[async_trait]
@@ -22,9 +32,52 @@ impl EventHandler for Handler {
 
 	async fn ready(
 		&self,
-		_ctx: Context,
+		ctx: Context,
 		data_about_bot: Ready,
 	) {
-		log::info!("{} está aqui!", data_about_bot.user.name)
+		log::info!("{} está aqui!", data_about_bot.user.name);
+
+		interaction_processing::register_commands(&ctx).await;
+
+		#[cfg(feature = "music")]
+		{
+			tokio::spawn(auto_leave::watch_idle(ctx.clone()));
+			tokio::spawn(panel::watch(ctx));
+		}
+	}
+
+	#[cfg(feature = "music")]
+	async fn cache_ready(
+		&self,
+		ctx: Context,
+		_guilds: Vec<GuildId>,
+	) {
+		persistence::restore_players(&ctx).await;
+	}
+
+	#[cfg(feature = "music")]
+	async fn voice_state_update(
+		&self,
+		ctx: Context,
+		guild_id: Option<GuildId>,
+		_old: Option<VoiceState>,
+		_new: VoiceState,
+	) {
+		if let Some(guild_id) = guild_id {
+			auto_leave::voice_state_update(&ctx, guild_id).await;
+		}
+	}
+
+	async fn interaction_create(
+		&self,
+		ctx: Context,
+		interaction: Interaction,
+	) {
+		match interaction {
+			Interaction::ApplicationCommand(command) => interaction_processing::dispatch(&ctx, &command).await,
+			#[cfg(feature = "music")]
+			Interaction::MessageComponent(component) => panel::handle_button(&ctx, &component).await,
+			_ => {}
+		}
 	}
 }
\ No newline at end of file
@@ -0,0 +1,304 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use rusqlite::{Connection, OptionalExtension, params, Transaction};
+use serenity::model::id::{GuildId, RoleId, UserId};
+use serenity::prelude::{Mutex, TypeMapKey};
+
+// Every entry is applied exactly once, in order. The index of the last applied
+// entry + 1 is stored in the `user_version` pragma of the database file.
+// Never edit an entry that was already released, append a new one instead.
+const MIGRATIONS: &[&str] = &[
+	"CREATE TABLE guild_settings (
+		guild_id INTEGER PRIMARY KEY NOT NULL,
+		prefix TEXT
+	);",
+	"ALTER TABLE guild_settings ADD COLUMN volume INTEGER;",
+	"ALTER TABLE guild_settings ADD COLUMN dj_role INTEGER;",
+	"CREATE TABLE playlist_tracks (
+		owner_kind TEXT NOT NULL,
+		owner_id INTEGER NOT NULL,
+		name TEXT NOT NULL COLLATE NOCASE,
+		position INTEGER NOT NULL,
+		track TEXT NOT NULL,
+		info TEXT NOT NULL,
+		PRIMARY KEY (owner_kind, owner_id, name, position)
+	);",
+	"CREATE TABLE player_states (
+		guild_id INTEGER PRIMARY KEY NOT NULL,
+		state TEXT NOT NULL
+	);",
+];
+
+/// Who a saved playlist belongs to.
+#[derive(Clone, Copy)]
+#[cfg_attr(not(feature = "music"), allow(dead_code))]
+pub enum PlaylistOwner {
+	User(UserId),
+	Guild(GuildId),
+}
+
+impl PlaylistOwner {
+	fn key(self) -> (&'static str, i64) {
+		match self {
+			PlaylistOwner::User(user_id) => ("user", user_id.0 as i64),
+			PlaylistOwner::Guild(guild_id) => ("guild", guild_id.0 as i64),
+		}
+	}
+}
+
+/// A track of a saved playlist, kept the way lavalink describes it so loading needs no search.
+#[cfg_attr(not(feature = "music"), allow(dead_code))]
+pub struct PlaylistTrack {
+	/// The id of the song, the encoded track string for lavalink.
+	pub track: String,
+	/// The track info as JSON.
+	pub info: String,
+}
+
+pub struct Database {
+	connection: Mutex<Connection>,
+}
+
+impl TypeMapKey for Database {
+	type Value = Arc<Database>;
+}
+
+impl Database {
+	pub fn open(path: impl AsRef<Path>) -> rusqlite::Result<Database> {
+		let mut connection = Connection::open(path)?;
+		migrate(&mut connection)?;
+
+		Ok(Database {
+			connection: Mutex::new(connection),
+		})
+	}
+
+	pub async fn guild_prefix(&self, guild_id: GuildId) -> rusqlite::Result<Option<String>> {
+		let connection = self.connection.lock().await;
+
+		let prefix = connection.query_row(
+			"SELECT prefix FROM guild_settings WHERE guild_id = ?",
+			params![guild_id.0 as i64],
+			|row| row.get(0),
+		).optional()?;
+
+		Ok(prefix.flatten())
+	}
+
+	/// Setting the prefix to `None` resets the guild to the prefix from the config.
+	pub async fn set_guild_prefix(&self, guild_id: GuildId, prefix: Option<&str>) -> rusqlite::Result<()> {
+		let connection = self.connection.lock().await;
+
+		connection.execute(
+			"INSERT INTO guild_settings (guild_id, prefix) VALUES (?1, ?2)
+			ON CONFLICT(guild_id) DO UPDATE SET prefix = excluded.prefix",
+			params![guild_id.0 as i64, prefix],
+		)?;
+
+		Ok(())
+	}
+
+	#[cfg_attr(not(feature = "music"), allow(dead_code))]
+	pub async fn guild_volume(&self, guild_id: GuildId) -> rusqlite::Result<Option<u16>> {
+		let connection = self.connection.lock().await;
+
+		let volume = connection.query_row(
+			"SELECT volume FROM guild_settings WHERE guild_id = ?",
+			params![guild_id.0 as i64],
+			|row| row.get(0),
+		).optional()?;
+
+		Ok(volume.flatten())
+	}
+
+	#[cfg_attr(not(feature = "music"), allow(dead_code))]
+	pub async fn set_guild_volume(&self, guild_id: GuildId, volume: u16) -> rusqlite::Result<()> {
+		let connection = self.connection.lock().await;
+
+		connection.execute(
+			"INSERT INTO guild_settings (guild_id, volume) VALUES (?1, ?2)
+			ON CONFLICT(guild_id) DO UPDATE SET volume = excluded.volume",
+			params![guild_id.0 as i64, volume],
+		)?;
+
+		Ok(())
+	}
+
+	#[cfg_attr(not(feature = "music"), allow(dead_code))]
+	pub async fn guild_dj_role(&self, guild_id: GuildId) -> rusqlite::Result<Option<RoleId>> {
+		let connection = self.connection.lock().await;
+
+		let dj_role: Option<Option<i64>> = connection.query_row(
+			"SELECT dj_role FROM guild_settings WHERE guild_id = ?",
+			params![guild_id.0 as i64],
+			|row| row.get(0),
+		).optional()?;
+
+		Ok(dj_role.flatten().map(|role_id| RoleId(role_id as u64)))
+	}
+
+	/// Setting the DJ role to `None` lets everyone use every music command again.
+	#[cfg_attr(not(feature = "music"), allow(dead_code))]
+	pub async fn set_guild_dj_role(&self, guild_id: GuildId, dj_role: Option<RoleId>) -> rusqlite::Result<()> {
+		let connection = self.connection.lock().await;
+
+		connection.execute(
+			"INSERT INTO guild_settings (guild_id, dj_role) VALUES (?1, ?2)
+			ON CONFLICT(guild_id) DO UPDATE SET dj_role = excluded.dj_role",
+			params![guild_id.0 as i64, dj_role.map(|role_id| role_id.0 as i64)],
+		)?;
+
+		Ok(())
+	}
+
+	/// The names of the playlists and how many tracks they have.
+	#[cfg_attr(not(feature = "music"), allow(dead_code))]
+	pub async fn playlists(&self, owner: PlaylistOwner) -> rusqlite::Result<Vec<(String, usize)>> {
+		let connection = self.connection.lock().await;
+		let (kind, id) = owner.key();
+
+		let mut statement = connection.prepare(
+			"SELECT name, COUNT(*) FROM playlist_tracks WHERE owner_kind = ?1 AND owner_id = ?2
+			GROUP BY name ORDER BY name",
+		)?;
+
+		let playlists = statement
+			.query_map(params![kind, id], |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as usize)))?
+			.collect();
+
+		playlists
+	}
+
+	/// Empty if there is no such playlist.
+	#[cfg_attr(not(feature = "music"), allow(dead_code))]
+	pub async fn playlist(&self, owner: PlaylistOwner, name: &str) -> rusqlite::Result<Vec<PlaylistTrack>> {
+		let connection = self.connection.lock().await;
+		let (kind, id) = owner.key();
+
+		let mut statement = connection.prepare(
+			"SELECT track, info FROM playlist_tracks WHERE owner_kind = ?1 AND owner_id = ?2 AND name = ?3
+			ORDER BY position",
+		)?;
+
+		let tracks = statement
+			.query_map(params![kind, id, name], |row| Ok(PlaylistTrack { track: row.get(0)?, info: row.get(1)? }))?
+			.collect();
+
+		tracks
+	}
+
+	/// Replaces the tracks of the playlist, creating it if it doesn't exist yet.
+	#[cfg_attr(not(feature = "music"), allow(dead_code))]
+	pub async fn save_playlist(&self, owner: PlaylistOwner, name: &str, tracks: &[PlaylistTrack]) -> rusqlite::Result<()> {
+		let mut connection = self.connection.lock().await;
+		let (kind, id) = owner.key();
+
+		let transaction = connection.transaction()?;
+		transaction.execute(
+			"DELETE FROM playlist_tracks WHERE owner_kind = ?1 AND owner_id = ?2 AND name = ?3",
+			params![kind, id, name],
+		)?;
+		insert_tracks(&transaction, owner, name, 0, tracks)?;
+		transaction.commit()
+	}
+
+	/// Adds tracks to the end of the playlist, creating it if it doesn't exist yet.
+	#[cfg_attr(not(feature = "music"), allow(dead_code))]
+	pub async fn add_to_playlist(&self, owner: PlaylistOwner, name: &str, tracks: &[PlaylistTrack]) -> rusqlite::Result<()> {
+		let mut connection = self.connection.lock().await;
+		let (kind, id) = owner.key();
+
+		let transaction = connection.transaction()?;
+		let next_position: i64 = transaction.query_row(
+			"SELECT COALESCE(MAX(position) + 1, 0) FROM playlist_tracks
+			WHERE owner_kind = ?1 AND owner_id = ?2 AND name = ?3",
+			params![kind, id, name],
+			|row| row.get(0),
+		)?;
+		insert_tracks(&transaction, owner, name, next_position, tracks)?;
+		transaction.commit()
+	}
+
+	/// Returns whether there was such a playlist.
+	#[cfg_attr(not(feature = "music"), allow(dead_code))]
+	pub async fn delete_playlist(&self, owner: PlaylistOwner, name: &str) -> rusqlite::Result<bool> {
+		let connection = self.connection.lock().await;
+		let (kind, id) = owner.key();
+
+		let deleted = connection.execute(
+			"DELETE FROM playlist_tracks WHERE owner_kind = ?1 AND owner_id = ?2 AND name = ?3",
+			params![kind, id, name],
+		)?;
+
+		Ok(deleted > 0)
+	}
+
+	/// Replaces the saved players with the given ones. Each state is the player of the guild as JSON.
+	#[cfg_attr(not(feature = "music"), allow(dead_code))]
+	pub async fn save_player_states(&self, states: &[(GuildId, String)]) -> rusqlite::Result<()> {
+		let mut connection = self.connection.lock().await;
+		let transaction = connection.transaction()?;
+
+		transaction.execute("DELETE FROM player_states", [])?;
+		for (guild_id, state) in states {
+			transaction.execute(
+				"INSERT INTO player_states (guild_id, state) VALUES (?, ?)",
+				params![guild_id.0 as i64, state],
+			)?;
+		}
+
+		transaction.commit()
+	}
+
+	/// Returns the saved players and removes them, so they are only restored once.
+	#[cfg_attr(not(feature = "music"), allow(dead_code))]
+	pub async fn take_player_states(&self) -> rusqlite::Result<Vec<(GuildId, String)>> {
+		let mut connection = self.connection.lock().await;
+		let transaction = connection.transaction()?;
+
+		let states = transaction
+			.prepare("SELECT guild_id, state FROM player_states")?
+			.query_map([], |row| Ok((GuildId(row.get::<_, i64>(0)? as u64), row.get(1)?)))?
+			.collect::<rusqlite::Result<Vec<_>>>()?;
+
+		transaction.execute("DELETE FROM player_states", [])?;
+		transaction.commit()?;
+
+		Ok(states)
+	}
+}
+
+fn insert_tracks(
+	transaction: &Transaction,
+	owner: PlaylistOwner,
+	name: &str,
+	first_position: i64,
+	tracks: &[PlaylistTrack],
+) -> rusqlite::Result<()> {
+	let (kind, id) = owner.key();
+	let mut statement = transaction.prepare(
+		"INSERT INTO playlist_tracks (owner_kind, owner_id, name, position, track, info)
+		VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+	)?;
+
+	for (position, track) in (first_position..).zip(tracks) {
+		statement.execute(params![kind, id, name, position, track.track, track.info])?;
+	}
+
+	Ok(())
+}
+
+fn migrate(connection: &mut Connection) -> rusqlite::Result<()> {
+	let version: usize = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
+	for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+		log::debug!("Applying database migration {}", index + 1);
+		let transaction = connection.transaction()?;
+		transaction.execute_batch(migration)?;
+		transaction.pragma_update(None, "user_version", &((index + 1) as i64))?;
+		transaction.commit()?;
+	}
+
+	Ok(())
+}
@@ -0,0 +1,729 @@
+use serenity::builder::CreateApplicationCommand;
+use serenity::framework::standard::{Command, CommandResult};
+use serenity::model::interactions::application_command::{
+	ApplicationCommand,
+	ApplicationCommandInteraction,
+	ApplicationCommandInteractionDataOption,
+	ApplicationCommandOptionType,
+};
+use serenity::prelude::*;
+
+use crate::commands::{hate::*, math::*, meta::*};
+use crate::invocation::Invocation;
+
+#[cfg(feature = "music")]
+use serenity::builder::CreateApplicationCommandOption;
+#[cfg(feature = "music")]
+use serenity::model::id::RoleId;
+#[cfg(feature = "music")]
+use crate::{
+	message_processing::MUSIC_GROUP,
+	music::{
+		clear::*,
+		dj::*,
+		export::*,
+		filter::*,
+		history::*,
+		info::*,
+		join::*,
+		leave::*,
+		local::*,
+		pause::*,
+		play::*,
+		playlist::*,
+		queue::*,
+		remove::*,
+		reorder::*,
+		repeat::*,
+		resume::*,
+		seek::*,
+		shuffle::*,
+		skip::*,
+		soundboard::*,
+		volume::*,
+		youtube_search::*,
+	},
+};
+
+/// Registers all commands that can be used as slash commands with discord.
+/// Descriptions are taken from the prefix commands so both stay the same.
+pub async fn register_commands(ctx: &Context) {
+	let result = ApplicationCommand::set_global_application_commands(&ctx.http, |commands| {
+		commands
+			.create_application_command(|command| describe(command, &PING_COMMAND))
+			.create_application_command(|command| describe(command, &LATENCY_COMMAND))
+			.create_application_command(|command| describe(command, &HATE_COMMAND))
+			.create_application_command(|command| describe(command, &INVITE_COMMAND))
+			.create_application_command(|command| {
+				command
+					.name("math")
+					.description("Math related commands")
+					.create_option(|option| {
+						option
+							.name("eval")
+							.description(short_description(&EVAL_COMMAND))
+							.kind(ApplicationCommandOptionType::SubCommand)
+							.create_sub_option(|option| {
+								option
+									.name("expression")
+									.description("The expression to evaluate")
+									.kind(ApplicationCommandOptionType::String)
+									.required(true)
+							})
+					})
+			});
+
+		#[cfg(feature = "music")]
+		commands
+			.create_application_command(|command| describe(command, &JOIN_COMMAND))
+			.create_application_command(|command| describe(command, &LEAVE_COMMAND))
+			.create_application_command(|command| {
+				describe(command, &PLAY_COMMAND)
+					.create_option(|option| {
+						option
+							.name("query")
+							.description("A link to a song or playlist, or what to search for on youtube")
+							.kind(ApplicationCommandOptionType::String)
+							.required(true)
+					})
+			})
+			.create_application_command(|command| describe(command, &SKIP_COMMAND))
+			.create_application_command(|command| describe(command, &INFO_COMMAND))
+			.create_application_command(|command| {
+				describe(command, &QUEUE_COMMAND)
+					.create_option(|option| {
+						option
+							.name("page")
+							.description("The page of the queue to show")
+							.kind(ApplicationCommandOptionType::Integer)
+							.min_int_value(1)
+					})
+					.create_option(|option| {
+						option
+							.name("export")
+							.description("Uploads the queue as a file instead")
+							.kind(ApplicationCommandOptionType::String)
+							.add_string_choice("json", "json")
+							.add_string_choice("m3u", "m3u")
+					})
+			})
+			.create_application_command(|command| describe(command, &CLEAR_COMMAND))
+			.create_application_command(|command| describe(command, &PAUSE_COMMAND))
+			.create_application_command(|command| describe(command, &UNPAUSE_COMMAND))
+			.create_application_command(|command| {
+				describe(command, &REMOVE_COMMAND)
+					.create_option(|option| {
+						option
+							.name("songs")
+							.description("Positions like `7`, `3-8` or `2 5 9`, a user mention or `dupes`")
+							.kind(ApplicationCommandOptionType::String)
+							.required(true)
+					})
+			})
+			.create_application_command(|command| {
+				describe(command, &MOVE_TRACK_COMMAND)
+					.create_option(|option| {
+						option
+							.name("from")
+							.description("The position of the song in the queue")
+							.kind(ApplicationCommandOptionType::Integer)
+							.min_int_value(1)
+							.required(true)
+					})
+					.create_option(|option| {
+						option
+							.name("to")
+							.description("The position to move the song to")
+							.kind(ApplicationCommandOptionType::Integer)
+							.min_int_value(1)
+							.required(true)
+					})
+			})
+			.create_application_command(|command| {
+				describe(command, &SKIPTO_COMMAND)
+					.create_option(|option| {
+						option
+							.name("index")
+							.description("The position of the song in the queue")
+							.kind(ApplicationCommandOptionType::Integer)
+							.min_int_value(1)
+							.required(true)
+					})
+			})
+			.create_application_command(|command| {
+				describe(command, &PLAYNEXT_COMMAND)
+					.create_option(|option| {
+						option
+							.name("query")
+							.description("A link to a song or playlist, or what to search for on youtube")
+							.kind(ApplicationCommandOptionType::String)
+							.required(true)
+					})
+			})
+			.create_application_command(|command| {
+				describe(command, &HISTORY_COMMAND)
+					.create_option(|option| {
+						option
+							.name("page")
+							.description("The page of the history to show")
+							.kind(ApplicationCommandOptionType::Integer)
+							.min_int_value(1)
+					})
+			})
+			.create_application_command(|command| describe(command, &PREVIOUS_COMMAND))
+			.create_application_command(|command| {
+				describe(command, &PLAYLIST_COMMAND)
+					.create_option(|option| {
+						option
+							.name("list")
+							.description("Lists your playlists and the ones of this server")
+							.kind(ApplicationCommandOptionType::SubCommand)
+					})
+					.create_option(|option| {
+						option
+							.name("save")
+							.description("Saves the queue as a playlist")
+							.kind(ApplicationCommandOptionType::SubCommand)
+							.create_sub_option(|option| {
+								option
+									.name("name")
+									.description("The name of the playlist")
+									.kind(ApplicationCommandOptionType::String)
+									.required(true)
+							})
+							.create_sub_option(|option| playlist_server_option(option))
+					})
+					.create_option(|option| {
+						option
+							.name("load")
+							.description("Adds the songs of a playlist to the queue")
+							.kind(ApplicationCommandOptionType::SubCommand)
+							.create_sub_option(|option| {
+								option
+									.name("name")
+									.description("The name of the playlist")
+									.kind(ApplicationCommandOptionType::String)
+									.required(true)
+							})
+							.create_sub_option(|option| playlist_server_option(option))
+					})
+					.create_option(|option| {
+						option
+							.name("add")
+							.description("Adds songs to a playlist")
+							.kind(ApplicationCommandOptionType::SubCommand)
+							.create_sub_option(|option| {
+								option
+									.name("name")
+									.description("The name of the playlist")
+									.kind(ApplicationCommandOptionType::String)
+									.required(true)
+							})
+							.create_sub_option(|option| {
+								option
+									.name("link")
+									.description("A link to a song or playlist, or what to search for on youtube")
+									.kind(ApplicationCommandOptionType::String)
+									.required(true)
+							})
+							.create_sub_option(|option| playlist_server_option(option))
+					})
+					.create_option(|option| {
+						option
+							.name("delete")
+							.description("Deletes a playlist")
+							.kind(ApplicationCommandOptionType::SubCommand)
+							.create_sub_option(|option| {
+								option
+									.name("name")
+									.description("The name of the playlist")
+									.kind(ApplicationCommandOptionType::String)
+									.required(true)
+							})
+							.create_sub_option(|option| playlist_server_option(option))
+					})
+			})
+			.create_application_command(|command| {
+				describe(command, &LOCAL_COMMAND)
+					.create_option(|option| {
+						option
+							.name("list")
+							.description("Lists the audio files of the media directory")
+							.kind(ApplicationCommandOptionType::SubCommand)
+							.create_sub_option(|option| {
+								option
+									.name("name")
+									.description("Only list the files matching this name")
+									.kind(ApplicationCommandOptionType::String)
+							})
+					})
+					.create_option(|option| {
+						option
+							.name("play")
+							.description("Adds the file matching the name best to the queue")
+							.kind(ApplicationCommandOptionType::SubCommand)
+							.create_sub_option(|option| {
+								option
+									.name("name")
+									.description("The name of the file, it doesn't have to be exact")
+									.kind(ApplicationCommandOptionType::String)
+									.required(true)
+							})
+					})
+			})
+			.create_application_command(|command| {
+				describe(command, &SOUNDBOARD_COMMAND)
+					.create_option(|option| {
+						option
+							.name("play")
+							.description("Plays a clip right away, the current song continues afterwards")
+							.kind(ApplicationCommandOptionType::SubCommand)
+							.create_sub_option(|option| {
+								option
+									.name("name")
+									.description("The name of the clip")
+									.kind(ApplicationCommandOptionType::String)
+									.required(true)
+							})
+					})
+					.create_option(|option| {
+						option
+							.name("list")
+							.description("Lists the clips of this server")
+							.kind(ApplicationCommandOptionType::SubCommand)
+					})
+					.create_option(|option| {
+						option
+							.name("remove")
+							.description("Deletes a clip")
+							.kind(ApplicationCommandOptionType::SubCommand)
+							.create_sub_option(|option| {
+								option
+									.name("name")
+									.description("The name of the clip")
+									.kind(ApplicationCommandOptionType::String)
+									.required(true)
+							})
+					})
+			})
+			.create_application_command(|command| describe(command, &SHUFFLE_COMMAND))
+			.create_application_command(|command| {
+				describe(command, &REPEAT_COMMAND)
+					.create_option(|option| {
+						option
+							.name("mode")
+							.description("What to loop, leave out to show the current mode")
+							.kind(ApplicationCommandOptionType::String)
+							.add_string_choice("off", "off")
+							.add_string_choice("track", "track")
+							.add_string_choice("queue", "queue")
+					})
+			})
+			.create_application_command(|command| {
+				describe(command, &YOUTUBE_SEARCH_COMMAND)
+					.create_option(|option| {
+						option
+							.name("query")
+							.description("What to search for")
+							.kind(ApplicationCommandOptionType::String)
+							.required(true)
+					})
+					.create_option(|option| {
+						option
+							.name("first")
+							.description("Adds the first result right away instead of letting you pick one")
+							.kind(ApplicationCommandOptionType::Boolean)
+					})
+			})
+			.create_application_command(|command| {
+				describe(command, &SEEK_COMMAND).create_option(|option| time_option(option, "The position to jump to, like `1:23` or `90s`"))
+			})
+			.create_application_command(|command| {
+				describe(command, &FORWARD_COMMAND).create_option(|option| time_option(option, "How far to skip ahead, like `30s` or `2m`"))
+			})
+			.create_application_command(|command| {
+				describe(command, &REWIND_COMMAND).create_option(|option| time_option(option, "How far to go back, like `10s` or `1:00`"))
+			})
+			.create_application_command(|command| {
+				describe(command, &VOLUME_COMMAND)
+					.create_option(|option| {
+						option
+							.name("volume")
+							.description("The new volume in percent, leave out to show the current one")
+							.kind(ApplicationCommandOptionType::Integer)
+							.min_int_value(0)
+							.max_int_value(MAX_VOLUME as i32)
+					})
+			})
+			.create_application_command(|command| {
+				describe(command, &FILTER_COMMAND)
+					.create_option(|option| {
+						option
+							.name("preset")
+							.description("Turns a filter preset on, or off if it is already on")
+							.kind(ApplicationCommandOptionType::SubCommand)
+							.create_sub_option(|option| {
+								option
+									.name("name")
+									.description("The preset to toggle")
+									.kind(ApplicationCommandOptionType::String)
+									.required(true)
+									.add_string_choice("bassboost", "bassboost")
+									.add_string_choice("nightcore", "nightcore")
+									.add_string_choice("vaporwave", "vaporwave")
+									.add_string_choice("8d", "8d")
+									.add_string_choice("karaoke", "karaoke")
+							})
+					})
+					.create_option(|option| {
+						option
+							.name("eq")
+							.description("Sets the gain of an equalizer band")
+							.kind(ApplicationCommandOptionType::SubCommand)
+							.create_sub_option(|option| {
+								option
+									.name("band")
+									.description("The band to change, lower bands are lower frequencies")
+									.kind(ApplicationCommandOptionType::Integer)
+									.required(true)
+									.min_int_value(0)
+									.max_int_value(EQ_BANDS as i32 - 1)
+							})
+							.create_sub_option(|option| {
+								option
+									.name("gain")
+									.description("The new gain, 0 is unchanged")
+									.kind(ApplicationCommandOptionType::Number)
+									.required(true)
+									.min_number_value(MIN_GAIN)
+									.max_number_value(MAX_GAIN)
+							})
+					})
+					.create_option(|option| {
+						option
+							.name("reset")
+							.description("Turns off all filters")
+							.kind(ApplicationCommandOptionType::SubCommand)
+					})
+					.create_option(|option| {
+						option
+							.name("show")
+							.description("Shows the active filters")
+							.kind(ApplicationCommandOptionType::SubCommand)
+					})
+			})
+			.create_application_command(|command| {
+				describe(command, &DJROLE_COMMAND)
+					.create_option(|option| {
+						option
+							.name("role")
+							.description("The new DJ role, leave out to show the current one")
+							.kind(ApplicationCommandOptionType::Role)
+					})
+					.create_option(|option| {
+						option
+							.name("reset")
+							.description("Removes the DJ role so everyone can control the music again")
+							.kind(ApplicationCommandOptionType::Boolean)
+					})
+			});
+
+		commands
+	}).await;
+
+	match result {
+		Ok(commands) => log::info!("Registered {} slash commands", commands.len()),
+		Err(why) => log::error!("Error registering slash commands: {:?}", why),
+	}
+}
+
+pub async fn dispatch(ctx: &Context, command: &ApplicationCommandInteraction) {
+	let name = command.data.name.as_str();
+	log::info!("Got slash command '{}' by user '{}'", name, command.user.name);
+
+	// Discord only waits 3 seconds for a response, which searching for a song can easily exceed
+	if let Err(why) = command.defer(&ctx.http).await {
+		log::error!("Error deferring slash command '{}': {:?}", name, why);
+
+		return;
+	}
+
+	let invocation = Invocation::from(command);
+
+	match run(ctx, command, &invocation).await {
+		Ok(()) => log::info!("Processed slash command '{}'", name),
+		Err(why) => {
+			log::error!("Slash command returned an error: {:?}", why);
+
+			// Commands that already told the user what went wrong don't need a generic message on top
+			if !invocation.has_responded() {
+				let _ = invocation.say(ctx, "Something went wrong while running this command").await;
+			}
+		}
+	}
+
+	// Otherwise the "thinking" placeholder would stay around forever
+	if !invocation.has_responded() {
+		let _ = command.delete_original_interaction_response(&ctx.http).await;
+	}
+}
+
+async fn run(ctx: &Context, command: &ApplicationCommandInteraction, invocation: &Invocation<'_>) -> CommandResult {
+	let name = command.data.name.as_str();
+	let options = &command.data.options;
+
+	// The framework checks `#[only_in(guilds)]` for message commands, slash commands have to do it here
+	#[cfg(feature = "music")]
+	if command.guild_id.is_none() && MUSIC_GROUP.options.commands.iter().any(|c| c.options.names.contains(&name)) {
+		invocation.say(ctx, "This command can only be used in a server").await?;
+
+		return Ok(());
+	}
+
+	// Slash commands can't use `#[checks(DJ)]`, so the DJ check runs here
+	#[cfg(feature = "music")]
+	if let (Some(guild_id), true) = (command.guild_id, DJ_COMMANDS.contains(&name)) {
+		let removal = if name == "remove" {
+			string_option(options, "songs").and_then(|songs| songs.parse().ok())
+		} else {
+			None
+		};
+
+		if let Err(reason) = check_dj(ctx, guild_id, command.user.id, name, removal.as_ref()).await {
+			invocation.reply(ctx, reason).await?;
+
+			return Ok(());
+		}
+	}
+
+	match name {
+		"ping" => handle_ping(ctx, invocation).await,
+		"latency" => handle_latency(ctx, invocation).await,
+		"hate" => handle_hate(ctx, invocation).await,
+		"invite" => handle_invite(ctx, invocation).await,
+		"math" => match options.first() {
+			Some(subcommand) if subcommand.name == "eval" => {
+				let expression = string_option(&subcommand.options, "expression").unwrap_or_default();
+				handle_eval(ctx, invocation, expression).await
+			}
+			_ => Ok(()),
+		},
+		#[cfg(feature = "music")]
+		"join" => handle_join(ctx, invocation).await,
+		#[cfg(feature = "music")]
+		"leave" => handle_leave(ctx, invocation).await,
+		#[cfg(feature = "music")]
+		"play" => handle_play(ctx, invocation, string_option(options, "query").unwrap_or_default()).await,
+		#[cfg(feature = "music")]
+		"skip" => handle_skip(ctx, invocation).await,
+		#[cfg(feature = "music")]
+		"info" => handle_info(ctx, invocation).await,
+		#[cfg(feature = "music")]
+		"queue" => match string_option(options, "export").and_then(|format| format.parse().ok()) {
+			Some(format) => handle_export(ctx, invocation, format).await,
+			None => handle_queue(ctx, invocation, integer_option(options, "page").unwrap_or(1) as usize).await,
+		},
+		#[cfg(feature = "music")]
+		"clear" => handle_clear(ctx, invocation).await,
+		#[cfg(feature = "music")]
+		"pause" => handle_pause(ctx, invocation).await,
+		#[cfg(feature = "music")]
+		"unpause" => handle_unpause(ctx, invocation).await,
+		#[cfg(feature = "music")]
+		"remove" => match string_option(options, "songs").unwrap_or_default().parse() {
+			Ok(removal) => handle_remove(ctx, invocation, removal).await,
+			Err(()) => {
+				invocation.say(ctx, "Invalid queue index").await?;
+
+				Ok(())
+			}
+		},
+		#[cfg(feature = "music")]
+		"move" => {
+			let from = integer_option(options, "from").unwrap_or(0) as usize;
+			handle_move(ctx, invocation, from, integer_option(options, "to").unwrap_or(0) as usize).await
+		}
+		#[cfg(feature = "music")]
+		"skipto" => handle_skipto(ctx, invocation, integer_option(options, "index").unwrap_or(0) as usize).await,
+		#[cfg(feature = "music")]
+		"playnext" => handle_playnext(ctx, invocation, string_option(options, "query").unwrap_or_default()).await,
+		#[cfg(feature = "music")]
+		"history" => handle_history(ctx, invocation, integer_option(options, "page").unwrap_or(1).max(1) as usize).await,
+		#[cfg(feature = "music")]
+		"previous" => handle_previous(ctx, invocation).await,
+		#[cfg(feature = "music")]
+		"playlist" => {
+			let subcommand = match options.first() {
+				Some(subcommand) => subcommand,
+				None => return Ok(()),
+			};
+
+			let name = string_option(&subcommand.options, "name").unwrap_or_default().to_string();
+			let server = bool_option(&subcommand.options, "server").unwrap_or(false);
+
+			let action = match subcommand.name.as_str() {
+				"save" => PlaylistAction::Save(name),
+				"load" => PlaylistAction::Load(name),
+				"add" => PlaylistAction::Add(name, string_option(&subcommand.options, "link").unwrap_or_default().to_string()),
+				"delete" => PlaylistAction::Delete(name),
+				_ => PlaylistAction::List,
+			};
+
+			handle_playlist(ctx, invocation, action, server).await
+		}
+		#[cfg(feature = "music")]
+		"local" => {
+			let action = match options.first() {
+				Some(subcommand) if subcommand.name == "play" => {
+					LocalAction::Play(string_option(&subcommand.options, "name").unwrap_or_default().to_string())
+				}
+				Some(subcommand) => LocalAction::List(string_option(&subcommand.options, "name").map(str::to_string)),
+				None => return Ok(()),
+			};
+
+			handle_local(ctx, invocation, action).await
+		}
+		#[cfg(feature = "music")]
+		"soundboard" => {
+			let subcommand = match options.first() {
+				Some(subcommand) => subcommand,
+				None => return Ok(()),
+			};
+
+			let name = string_option(&subcommand.options, "name").unwrap_or_default().to_lowercase();
+
+			let action = match subcommand.name.as_str() {
+				"play" => SoundboardAction::Play(name),
+				"remove" => SoundboardAction::Remove(name),
+				_ => SoundboardAction::List,
+			};
+
+			handle_soundboard(ctx, invocation, action).await
+		}
+		#[cfg(feature = "music")]
+		"shuffle" => handle_shuffle(ctx, invocation).await,
+		#[cfg(feature = "music")]
+		"loop" => handle_loop(ctx, invocation, string_option(options, "mode").and_then(|mode| mode.parse().ok())).await,
+		#[cfg(feature = "music")]
+		"seek" => handle_seek(ctx, invocation, string_option(options, "time").unwrap_or_default()).await,
+		#[cfg(feature = "music")]
+		"forward" => handle_forward(ctx, invocation, string_option(options, "time").unwrap_or_default()).await,
+		#[cfg(feature = "music")]
+		"rewind" => handle_rewind(ctx, invocation, string_option(options, "time").unwrap_or_default()).await,
+		#[cfg(feature = "music")]
+		"volume" => handle_volume(ctx, invocation, integer_option(options, "volume").map(|volume| volume.clamp(0, u16::MAX as i64) as u16)).await,
+		#[cfg(feature = "music")]
+		"filter" => {
+			let change = match options.first() {
+				Some(subcommand) if subcommand.name == "preset" => string_option(&subcommand.options, "name")
+					.and_then(|name| name.parse().ok())
+					.map(FilterChange::Toggle),
+				Some(subcommand) if subcommand.name == "eq" => Some(FilterChange::Band(
+					integer_option(&subcommand.options, "band").unwrap_or(0) as usize,
+					number_option(&subcommand.options, "gain").unwrap_or(0.0),
+				)),
+				Some(subcommand) if subcommand.name == "reset" => Some(FilterChange::Reset),
+				_ => None,
+			};
+
+			handle_filter(ctx, invocation, change).await
+		}
+		#[cfg(feature = "music")]
+		"djrole" => {
+			let role_id = if bool_option(options, "reset").unwrap_or(false) {
+				Some(None)
+			} else {
+				string_option(options, "role")
+					.and_then(|role_id| role_id.parse().ok())
+					.map(|role_id| Some(RoleId(role_id)))
+			};
+
+			handle_dj_role(ctx, invocation, role_id).await
+		}
+		#[cfg(feature = "music")]
+		"youtube_search" => {
+			let query = string_option(options, "query").unwrap_or_default();
+			handle_youtube_search(ctx, invocation, query, bool_option(options, "first").unwrap_or(false)).await
+		}
+		_ => {
+			log::warn!("Got unknown slash command '{}'", name);
+
+			Ok(())
+		}
+	}
+}
+
+fn describe<'a>(builder: &'a mut CreateApplicationCommand, command: &Command) -> &'a mut CreateApplicationCommand {
+	builder
+		.name(command.options.names[0])
+		.description(short_description(command))
+}
+
+/// Slash command descriptions are limited to a single line of 100 characters,
+/// so only the first sentence of longer descriptions is used.
+fn short_description(command: &Command) -> String {
+	let description = command.options.desc.unwrap_or("").lines().next().unwrap_or("");
+
+	let description = if description.chars().count() > 100 {
+		match description.find(". ") {
+			Some(end) => &description[..=end],
+			None => description,
+		}
+	} else {
+		description
+	};
+
+	description.chars().take(100).collect()
+}
+
+fn string_option<'a>(options: &'a [ApplicationCommandInteractionDataOption], name: &str) -> Option<&'a str> {
+	options
+		.iter()
+		.find(|option| option.name == name)
+		.and_then(|option| option.value.as_ref())
+		.and_then(|value| value.as_str())
+}
+
+#[cfg(feature = "music")]
+fn time_option<'a>(option: &'a mut CreateApplicationCommandOption, description: &str) -> &'a mut CreateApplicationCommandOption {
+	option
+		.name("time")
+		.description(description)
+		.kind(ApplicationCommandOptionType::String)
+		.required(true)
+}
+
+#[cfg(feature = "music")]
+fn integer_option(options: &[ApplicationCommandInteractionDataOption], name: &str) -> Option<i64> {
+	options
+		.iter()
+		.find(|option| option.name == name)
+		.and_then(|option| option.value.as_ref())
+		.and_then(|value| value.as_i64())
+}
+
+#[cfg(feature = "music")]
+fn playlist_server_option(option: &mut CreateApplicationCommandOption) -> &mut CreateApplicationCommandOption {
+	option
+		.name("server")
+		.description("Use the playlists of this server instead of your own")
+		.kind(ApplicationCommandOptionType::Boolean)
+}
+
+#[cfg(feature = "music")]
+fn bool_option(options: &[ApplicationCommandInteractionDataOption], name: &str) -> Option<bool> {
+	options
+		.iter()
+		.find(|option| option.name == name)
+		.and_then(|option| option.value.as_ref())
+		.and_then(|value| value.as_bool())
+}
+
+#[cfg(feature = "music")]
+fn number_option(options: &[ApplicationCommandInteractionDataOption], name: &str) -> Option<f64> {
+	options
+		.iter()
+		.find(|option| option.name == name)
+		.and_then(|option| option.value.as_ref())
+		.and_then(|value| value.as_f64())
+}
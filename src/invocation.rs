@@ -0,0 +1,218 @@
+use std::borrow::Cow;
+use std::fmt::Display;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serenity::builder::{CreateComponents, CreateEmbed};
+use serenity::model::interactions::application_command::ApplicationCommandInteraction;
+use serenity::model::interactions::message_component::MessageComponentInteraction;
+use serenity::http::AttachmentType;
+use serenity::model::prelude::{ChannelId, GuildId, Message, User};
+use serenity::prelude::Context;
+use serenity::Result;
+
+/// The message, slash command or button a command was invoked with.
+///
+/// Commands reply through this instead of the channel directly,
+/// so they behave the same no matter how they were invoked.
+pub struct Invocation<'a> {
+	source: Source<'a>,
+	responded: AtomicBool,
+}
+
+enum Source<'a> {
+	Message(&'a Message),
+	Interaction(&'a ApplicationCommandInteraction),
+	Component(&'a MessageComponentInteraction),
+}
+
+impl<'a> From<&'a Message> for Invocation<'a> {
+	fn from(msg: &'a Message) -> Self {
+		Invocation {
+			source: Source::Message(msg),
+			responded: AtomicBool::new(false),
+		}
+	}
+}
+
+impl<'a> From<&'a ApplicationCommandInteraction> for Invocation<'a> {
+	fn from(command: &'a ApplicationCommandInteraction) -> Self {
+		Invocation {
+			source: Source::Interaction(command),
+			responded: AtomicBool::new(false),
+		}
+	}
+}
+
+impl<'a> From<&'a MessageComponentInteraction> for Invocation<'a> {
+	fn from(component: &'a MessageComponentInteraction) -> Self {
+		Invocation {
+			source: Source::Component(component),
+			responded: AtomicBool::new(false),
+		}
+	}
+}
+
+impl Invocation<'_> {
+	#[cfg_attr(not(feature = "music"), allow(dead_code))]
+	pub fn guild_id(&self) -> Option<GuildId> {
+		match self.source {
+			Source::Message(msg) => msg.guild_id,
+			Source::Interaction(command) => command.guild_id,
+			Source::Component(component) => component.guild_id,
+		}
+	}
+
+	#[cfg_attr(not(feature = "music"), allow(dead_code))]
+	pub fn channel_id(&self) -> ChannelId {
+		match self.source {
+			Source::Message(msg) => msg.channel_id,
+			Source::Interaction(command) => command.channel_id,
+			Source::Component(component) => component.channel_id,
+		}
+	}
+
+	#[cfg_attr(not(feature = "music"), allow(dead_code))]
+	pub fn author(&self) -> &User {
+		match self.source {
+			Source::Message(msg) => &msg.author,
+			Source::Interaction(command) => &command.user,
+			Source::Component(component) => &component.user,
+		}
+	}
+
+	/// Whether anything was sent in response yet.
+	pub fn has_responded(&self) -> bool {
+		self.responded.load(Ordering::SeqCst)
+	}
+
+	/// Sends a message into the channel the command was invoked in.
+	pub async fn say(&self, ctx: &Context, content: impl Display) -> Result<Message> {
+		// Slash commands get deferred before they run, the first response
+		// replaces the "thinking" placeholder and everything after is a followup.
+		let responded = self.responded.swap(true, Ordering::SeqCst);
+
+		match self.source {
+			Source::Message(msg) => msg.channel_id.say(&ctx.http, content).await,
+			Source::Interaction(command) if responded => {
+				command.create_followup_message(&ctx.http, |followup| followup.content(content)).await
+			}
+			Source::Interaction(command) => {
+				command.edit_original_interaction_response(&ctx.http, |response| response.content(content)).await
+			}
+			Source::Component(component) if responded => {
+				component.create_followup_message(&ctx.http, |followup| followup.content(content)).await
+			}
+			Source::Component(component) => {
+				component.edit_original_interaction_response(&ctx.http, |response| response.content(content)).await
+			}
+		}
+	}
+
+	/// Like [`Invocation::say`], but mentions the author for message commands.
+	pub async fn reply(&self, ctx: &Context, content: impl Display) -> Result<Message> {
+		match self.source {
+			Source::Message(msg) => {
+				self.responded.store(true, Ordering::SeqCst);
+				msg.reply(ctx, content).await
+			}
+			Source::Interaction(_) | Source::Component(_) => self.say(ctx, content).await,
+		}
+	}
+
+	pub async fn send_embed<F>(&self, ctx: &Context, f: F) -> Result<Message>
+		where F: FnOnce(&mut CreateEmbed) -> &mut CreateEmbed
+	{
+		let responded = self.responded.swap(true, Ordering::SeqCst);
+
+		match self.source {
+			Source::Message(msg) => msg.channel_id.send_message(&ctx.http, |message| message.embed(f)).await,
+			Source::Interaction(command) if responded => {
+				command.create_followup_message(&ctx.http, |followup| followup.create_embed(f)).await
+			}
+			Source::Interaction(command) => {
+				command.edit_original_interaction_response(&ctx.http, |response| response.create_embed(f)).await
+			}
+			Source::Component(component) if responded => {
+				component.create_followup_message(&ctx.http, |followup| followup.create_embed(f)).await
+			}
+			Source::Component(component) => {
+				component.edit_original_interaction_response(&ctx.http, |response| response.create_embed(f)).await
+			}
+		}
+	}
+
+	/// Like [`Invocation::send_embed`], with buttons or other components below the embed.
+	#[cfg_attr(not(feature = "music"), allow(dead_code))]
+	pub async fn send_embed_with_components<F, C>(&self, ctx: &Context, f: F, components: C) -> Result<Message>
+		where F: FnOnce(&mut CreateEmbed) -> &mut CreateEmbed,
+			  C: FnOnce(&mut CreateComponents) -> &mut CreateComponents
+	{
+		let responded = self.responded.swap(true, Ordering::SeqCst);
+
+		match self.source {
+			Source::Message(msg) => {
+				msg.channel_id.send_message(&ctx.http, |message| message.embed(f).components(components)).await
+			}
+			Source::Interaction(command) if responded => {
+				command.create_followup_message(&ctx.http, |followup| followup.create_embed(f).components(components)).await
+			}
+			Source::Interaction(command) => {
+				command.edit_original_interaction_response(&ctx.http, |response| {
+					response.create_embed(f).components(components)
+				}).await
+			}
+			Source::Component(component) if responded => {
+				component.create_followup_message(&ctx.http, |followup| followup.create_embed(f).components(components)).await
+			}
+			Source::Component(component) => {
+				component.edit_original_interaction_response(&ctx.http, |response| {
+					response.create_embed(f).components(components)
+				}).await
+			}
+		}
+	}
+
+	/// Uploads a file into the channel the command was invoked in.
+	#[cfg_attr(not(feature = "music"), allow(dead_code))]
+	pub async fn send_file(&self, ctx: &Context, content: impl Display, filename: &str, data: Vec<u8>) -> Result<Message> {
+		let file = AttachmentType::Bytes {
+			data: Cow::Owned(data),
+			filename: filename.to_string(),
+		};
+
+		match self.source {
+			Source::Message(msg) => {
+				self.responded.store(true, Ordering::SeqCst);
+				msg.channel_id.send_files(&ctx.http, vec![file], |message| message.content(content)).await
+			}
+			// serenity drops files of interaction responses, so the file is sent as a regular message
+			Source::Interaction(_) | Source::Component(_) => {
+				self.say(ctx, content).await?;
+				self.channel_id().send_files(&ctx.http, vec![file], |message| message).await
+			}
+		}
+	}
+
+	/// Removes the components of a message sent by this invocation, for example once they expired.
+	#[cfg_attr(not(feature = "music"), allow(dead_code))]
+	pub async fn remove_components(&self, ctx: &Context, message: &mut Message) -> Result<()> {
+		match self.source {
+			Source::Message(_) => message.edit(ctx, |message| message.components(|components| components)).await,
+			// Responses to slash commands belong to the interaction's webhook and can only be edited through it
+			Source::Interaction(command) => {
+				*message = command.edit_followup_message(&ctx.http, message.id, |followup| {
+					followup.components(|components| components)
+				}).await?;
+
+				Ok(())
+			}
+			Source::Component(component) => {
+				*message = component.edit_followup_message(&ctx.http, message.id, |followup| {
+					followup.components(|components| components)
+				}).await?;
+
+				Ok(())
+			}
+		}
+	}
+}
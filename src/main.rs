@@ -15,13 +15,19 @@ use serenity::{
 };
 use serenity::client::bridge::gateway::GatewayIntents;
 use simple_logger::SimpleLogger;
-use tokio;
 
 #[cfg(feature = "music")]
 use {
-	crate::commands::music::handlers::{Lavalink, LavalinkHandler},
-	lavalink_rs::LavalinkClient,
-	songbird::SerenityInit,
+	crate::commands::music::auto_leave::AutoLeave,
+	crate::commands::music::backend::{Backend, MediaRoots},
+	crate::commands::music::backend::lavalink::LavalinkBackend,
+	crate::commands::music::backend::native::SongbirdBackend,
+	crate::commands::music::handlers::Lavalink,
+	crate::commands::music::nodes::LavalinkNodes,
+	crate::commands::music::panel::PanelUpdater,
+	crate::commands::music::persistence::save_players,
+	crate::config::BackendKind,
+	songbird::{SerenityInit, SongbirdKey},
 };
 use commands::*;
 use help::*;
@@ -30,12 +36,16 @@ use message_processing::*;
 use crate::commands::hate::HateMessage;
 use crate::config::ConfigData;
 use crate::custom_event_handlers::Handler;
+use crate::database::Database;
 
 mod custom_event_handlers;
+mod interaction_processing;
+mod invocation;
 mod commands;
 mod message_processing;
 mod help;
 mod config;
+mod database;
 
 
 struct ShardManagerContainer;
@@ -52,11 +62,14 @@ async fn main() {
 		.init()
 		.unwrap();
 
-	let config_data = config::read_config();
+	let config_data = match config::read_config() {
+		Ok(config_data) => config_data,
+		Err(why) => panic!("{}", why),
+	};
 
 	let http = Http::new_with_token(&config_data.general.token);
 
-	let (owners, bot_id) = match http.get_current_application_info().await {
+	let (owners, bot_id, application_id) = match http.get_current_application_info().await {
 		Ok(info) => {
 			let mut owners = HashSet::new();
 			if let Some(team) = info.team {
@@ -65,20 +78,26 @@ async fn main() {
 				owners.insert(info.owner.id);
 			}
 			match http.get_current_user().await {
-				Ok(bot_id) => (owners, bot_id.id),
+				Ok(bot_id) => (owners, bot_id.id, info.id),
 				Err(why) => panic!("Could not access the bot id: {:?}", why),
 			}
 		}
 		Err(why) => panic!("Could not access application info: {:?}", why),
 	};
 
-	let prefix = &config_data.general.prefix;
+	let database = match Database::open(&config_data.general.database) {
+		Ok(database) => Arc::new(database),
+		Err(why) => panic!("Could not open the database '{}': {:?}", config_data.general.database, why),
+	};
 
 	let framework = StandardFramework::new()
 		.configure(|c| c
 			.with_whitespace(true)
 			.on_mention(Some(bot_id))
-			.prefix(&prefix)
+			// The prefix is looked up per guild, falling back to the one from the config.
+			// The static prefix is disabled, otherwise it would stay usable next to a custom one.
+			.dynamic_prefix(dynamic_prefix)
+			.prefix("")
 			// In this case, if "," would be first, a message would never
 			// be delimited at ", ", forcing you to trim your arguments if you
 			// want to avoid whitespaces at the start of each.
@@ -131,12 +150,15 @@ async fn main() {
 		// #name is turned all uppercase
 		.help(&MY_HELP)
 		.group(&GENERAL_GROUP)
-		.group(&MATH_GROUP);
+		.group(&MATH_GROUP)
+		.group(&SETTINGS_GROUP);
 
 	#[cfg(feature = "music")]
 		let framework = framework.group(&MUSIC_GROUP);
 
 	let client = Client::builder(&config_data.general.token)
+		// Needed to register and answer slash commands
+		.application_id(application_id.0)
 		.event_handler(Handler)
 		.framework(framework)
 		.intents(GatewayIntents::all()); //change to only require the intents we actually want
@@ -149,25 +171,29 @@ async fn main() {
 	#[cfg(feature = "music")]
 		{
 			log::info!("Music feature active");
-			let lavalink_password = &config_data
-				.music
-				.as_ref()
-				.expect("Lavalink password missing from config")
-				.lavalink_password;
-
-			let lava_client = match LavalinkClient::builder(bot_id)
-				.set_host("127.0.0.1")
-				.set_password(lavalink_password)
-				.build(LavalinkHandler)
-				.await {
-				Ok(client) => client,
-				Err(why) => panic!("Error with lavalink client: {:?}", why)
-			};
-
-			{
-				let mut data = client.data.write().await;
-				data.insert::<Lavalink>(lava_client);
+			// Checked while reading the config
+			let music_config = config_data.music.as_ref().unwrap();
+
+			match music_config.backend {
+				BackendKind::Lavalink => {
+					let lavalink_nodes = Arc::new(LavalinkNodes::connect(bot_id, music_config.nodes()).await);
+
+					let mut data = client.data.write().await;
+					let songbird = data.get::<SongbirdKey>().unwrap().clone();
+					tokio::spawn(Arc::clone(&lavalink_nodes).watch(songbird));
+					data.insert::<Lavalink>(Arc::clone(&lavalink_nodes));
+					data.insert::<Backend>(Arc::new(LavalinkBackend::new(lavalink_nodes, MediaRoots::new(music_config))));
+				}
+				BackendKind::Songbird => {
+					let mut data = client.data.write().await;
+					let songbird = data.get::<SongbirdKey>().unwrap().clone();
+					data.insert::<Backend>(Arc::new(SongbirdBackend::new(songbird, MediaRoots::new(music_config))));
+				}
 			}
+
+			let mut data = client.data.write().await;
+			data.insert::<AutoLeave>(Arc::new(AutoLeave::default()));
+			data.insert::<PanelUpdater>(Arc::new(PanelUpdater::default()));
 		}
 
 	{
@@ -176,9 +202,52 @@ async fn main() {
 		data.insert::<ShardManagerContainer>(Arc::clone(&client.shard_manager));
 		data.insert::<HateMessage>(hate_messages);
 		data.insert::<ConfigData>(config_data);
+		data.insert::<Database>(database);
 	}
 
+	let shard_manager = Arc::clone(&client.shard_manager);
+	#[cfg(feature = "music")]
+		let data = Arc::clone(&client.data);
+
+	tokio::spawn(async move {
+		wait_for_shutdown().await;
+		log::info!("Shutting down");
+
+		#[cfg(feature = "music")]
+			save_players(&data).await;
+
+		shard_manager.lock().await.shutdown_all().await;
+	});
+
 	if let Err(why) = client.start().await {
 		log::error!("Client error: {:?}", why)
 	}
-}
\ No newline at end of file
+}
+
+/// Resolves once the process is asked to stop, by Ctrl+C or SIGTERM.
+async fn wait_for_shutdown() {
+	#[cfg(unix)]
+		{
+			use tokio::signal::unix::{signal, SignalKind};
+
+			let mut terminate = match signal(SignalKind::terminate()) {
+				Ok(terminate) => terminate,
+				Err(why) => {
+					log::error!("Could not listen for SIGTERM: {:?}", why);
+					let _ = tokio::signal::ctrl_c().await;
+
+					return;
+				}
+			};
+
+			tokio::select! {
+				_ = tokio::signal::ctrl_c() => {}
+				_ = terminate.recv() => {}
+			}
+		}
+
+	#[cfg(not(unix))]
+		{
+			let _ = tokio::signal::ctrl_c().await;
+		}
+}
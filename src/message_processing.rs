@@ -5,6 +5,7 @@ use serenity::{
 	framework::standard::{
 		CommandResult,
 		DispatchError,
+		Reason,
 		macros::{group, hook},
 	},
 	model::{
@@ -12,21 +13,33 @@ use serenity::{
 	},
 };
 
-use crate::commands::{hate::*, math::*, meta::*};
+use crate::commands::{hate::*, math::*, meta::*, settings::*};
+use crate::config::ConfigData;
+use crate::database::Database;
 
 #[cfg(feature = "music")]
 use crate::music::{
 	clear::*,
+	dj::*,
+	filter::*,
+	history::*,
 	info::*,
 	join::*,
 	leave::*,
+	local::*,
 	pause::*,
 	play::*,
+	playlist::*,
 	queue::*,
 	remove::*,
+	reorder::*,
+	repeat::*,
 	resume::*,
+	seek::*,
 	shuffle::*,
 	skip::*,
+	soundboard::*,
+	volume::*,
 	youtube_search::*,
 };
 
@@ -39,12 +52,34 @@ pub struct General;
 #[commands(eval)]
 struct Math;
 
+#[group]
+#[only_in(guilds)]
+#[required_permissions("ADMINISTRATOR")]
+#[commands(prefix)]
+struct Settings;
+
 #[cfg(feature = "music")]
 #[group]
 #[only_in(guilds)]
-#[commands(join, leave, play, skip, info, queue, clear, pause, unpause, remove, shuffle, youtube_search)]
+#[commands(join, leave, play, skip, info, queue, clear, pause, unpause, remove, shuffle, youtube_search, repeat, seek, forward, rewind, volume, filter, djrole, move_track, skipto, playnext, history, previous, playlist, local, soundboard)]
 pub struct Music;
 
+#[hook]
+pub async fn dynamic_prefix(ctx: &Context, msg: &Message) -> Option<String> {
+	let data = ctx.data.read().await;
+
+	if let Some(guild_id) = msg.guild_id {
+		let database = data.get::<Database>().unwrap();
+		match database.guild_prefix(guild_id).await {
+			Ok(Some(prefix)) => return Some(prefix),
+			Ok(None) => {}
+			Err(why) => log::error!("Error reading the prefix of guild {}: {:?}", guild_id, why),
+		}
+	}
+
+	Some(data.get::<ConfigData>().unwrap().general.prefix.clone())
+}
+
 #[hook]
 pub async fn before(_ctx: &Context, msg: &Message, command_name: &str) -> bool {
 	log::info!("Got command '{}' by user '{}'", command_name, msg.author.name);
@@ -79,14 +114,18 @@ pub async fn normal_message(_ctx: &Context, msg: &Message) {
 
 #[hook]
 pub async fn dispatch_error(ctx: &Context, msg: &Message, error: DispatchError) {
-	if let DispatchError::Ratelimited(info) = error {
+	match error {
 		// We notify them only once.
-		if info.is_first_try {
+		DispatchError::Ratelimited(info) if info.is_first_try => {
 			let _ = msg
 				.channel_id
 				.say(&ctx.http, &format!("Try this again in {} seconds.", info.as_secs()))
 				.await;
 		}
+		DispatchError::CheckFailed(_, Reason::User(reason)) => {
+			let _ = msg.reply(&ctx.http, reason).await;
+		}
+		_ => {}
 	}
 }
 